@@ -14,12 +14,12 @@ impl<'a> BtfIndex<'a> {
         let mut index = BtfIndex {
             name_index: HashMap::new(),
         };
-        for (i, t) in btf.types().iter().enumerate() {
+        for (i, t) in btf.iter() {
             let e = index
                 .name_index
                 .entry(&t.name())
                 .or_insert_with(|| Vec::new());
-            e.push(i as u32);
+            e.push(i);
         }
         index
     }
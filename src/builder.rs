@@ -0,0 +1,246 @@
+use crate::types::*;
+use crate::{btf_error, BtfError, BtfResult};
+
+/// Constructs BTF programmatically, type by type, e.g., to produce synthetic BTF for tests or
+/// to encode it with `Btf::to_bytes()`. Each `add_*()` method returns ID of added type. Types
+/// can only reference types added before them (or void), so self-referencing structs have to
+/// be referenced through forward declarations.
+#[derive(Debug)]
+pub struct BtfBuilder<'a> {
+    types: Vec<BtfType<'a>>,
+    ptr_sz: u32,
+    endian: scroll::Endian,
+}
+
+impl<'a> Default for BtfBuilder<'a> {
+    fn default() -> Self {
+        BtfBuilder::new()
+    }
+}
+
+impl<'a> BtfBuilder<'a> {
+    /// Builder of little-endian BTF for a target with 8-byte pointers
+    pub fn new() -> BtfBuilder<'a> {
+        BtfBuilder::with_target(8, scroll::LE)
+    }
+
+    pub fn with_target(ptr_sz: u32, endian: scroll::Endian) -> BtfBuilder<'a> {
+        BtfBuilder {
+            types: vec![BtfType::Void],
+            ptr_sz,
+            endian,
+        }
+    }
+
+    /// Adds arbitrary type, failing if it references a type that wasn't added yet.
+    pub fn add(&mut self, t: BtfType<'a>) -> BtfResult<u32> {
+        let id = self.types.len() as u32;
+        let mut bad_id = None;
+        t.visit_type_ids(|type_id| {
+            if type_id >= id && bad_id.is_none() {
+                bad_id = Some(type_id);
+            }
+        });
+        if let Some(type_id) = bad_id {
+            return Err(Box::new(BtfError::BadTypeId { id, type_id }));
+        }
+        if let BtfType::Void = t {
+            return btf_error("Void type can only be at ID 0".to_owned());
+        }
+        self.types.push(t);
+        Ok(id)
+    }
+
+    /// Adds int of given width in bits, occupying the smallest power-of-2 number of bytes fitting it.
+    pub fn add_int(
+        &mut self,
+        name: &'a str,
+        bits: u32,
+        encoding: BtfIntEncoding,
+    ) -> BtfResult<u32> {
+        if bits == 0 || bits > 128 {
+            return btf_error(format!("Int '{}' can't be {} bits wide", name, bits));
+        }
+        self.add(BtfType::Int(BtfInt {
+            name,
+            sz: bits.div_ceil(8).next_power_of_two(),
+            bits,
+            offset: 0,
+            encoding,
+        }))
+    }
+
+    pub fn add_float(&mut self, name: &'a str, sz: u32) -> BtfResult<u32> {
+        self.add(BtfType::Float(BtfFloat { name, sz }))
+    }
+
+    pub fn add_ptr(&mut self, type_id: u32) -> BtfResult<u32> {
+        self.add(BtfType::Ptr(BtfPtr { type_id }))
+    }
+
+    pub fn add_const(&mut self, type_id: u32) -> BtfResult<u32> {
+        self.add(BtfType::Const(BtfConst { type_id }))
+    }
+
+    pub fn add_volatile(&mut self, type_id: u32) -> BtfResult<u32> {
+        self.add(BtfType::Volatile(BtfVolatile { type_id }))
+    }
+
+    pub fn add_typedef(&mut self, name: &'a str, type_id: u32) -> BtfResult<u32> {
+        self.add(BtfType::Typedef(BtfTypedef { name, type_id }))
+    }
+
+    /// Adds array of `nelems` elements of type `val_type_id`, indexed by int `idx_type_id`.
+    pub fn add_array(&mut self, val_type_id: u32, idx_type_id: u32, nelems: u32) -> BtfResult<u32> {
+        self.add(BtfType::Array(BtfArray {
+            nelems,
+            idx_type_id,
+            val_type_id,
+        }))
+    }
+
+    pub fn add_struct(
+        &mut self,
+        name: &'a str,
+        sz: u32,
+        members: Vec<BtfMember<'a>>,
+    ) -> BtfResult<u32> {
+        self.add(BtfType::Struct(BtfComposite {
+            is_struct: true,
+            name,
+            sz,
+            members,
+        }))
+    }
+
+    pub fn add_union(
+        &mut self,
+        name: &'a str,
+        sz: u32,
+        members: Vec<BtfMember<'a>>,
+    ) -> BtfResult<u32> {
+        self.add(BtfType::Union(BtfComposite {
+            is_struct: false,
+            name,
+            sz,
+            members,
+        }))
+    }
+
+    /// Adds enum of given size in bytes, which is encoded as 64-bit enum if it's 8 bytes. Enum
+    /// is marked signed if any of its values is negative.
+    pub fn add_enum(
+        &mut self,
+        name: &'a str,
+        sz: u32,
+        values: Vec<BtfEnumValue<'a>>,
+    ) -> BtfResult<u32> {
+        let t = BtfEnum {
+            name,
+            sz,
+            is_64: sz == 8,
+            is_signed: values.iter().any(|v| v.value < 0),
+            values,
+        };
+        self.add(if t.is_64 {
+            BtfType::Enum64(t)
+        } else {
+            BtfType::Enum(t)
+        })
+    }
+
+    pub fn add_fwd(&mut self, name: &'a str, kind: BtfFwdKind) -> BtfResult<u32> {
+        self.add(BtfType::Fwd(BtfFwd { name, kind }))
+    }
+
+    pub fn add_func_proto(
+        &mut self,
+        res_type_id: u32,
+        params: Vec<BtfFuncParam<'a>>,
+    ) -> BtfResult<u32> {
+        self.add(BtfType::FuncProto(BtfFuncProto {
+            res_type_id,
+            params,
+        }))
+    }
+
+    pub fn add_func(
+        &mut self,
+        name: &'a str,
+        proto_type_id: u32,
+        kind: BtfFuncKind,
+    ) -> BtfResult<u32> {
+        self.add(BtfType::Func(BtfFunc {
+            name,
+            proto_type_id,
+            kind,
+        }))
+    }
+
+    /// Finishes building, returning BTF with all the added types. Resulting BTF has no string
+    /// section until it's encoded with `Btf::to_bytes()` and loaded back.
+    pub fn build(self) -> Btf<'a> {
+        Btf::from_types(self.types, self.ptr_sz, self.endian)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_round_trip() {
+        let mut b = BtfBuilder::new();
+        let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+        let uchar = b.add_int("unsigned char", 8, BtfIntEncoding::None).unwrap();
+        let fwd = b.add_fwd("list", BtfFwdKind::Struct).unwrap();
+        let next = b.add_ptr(fwd).unwrap();
+        let arr = b.add_array(uchar, int, 4).unwrap();
+        let members = vec![
+            BtfMember {
+                name: "next",
+                type_id: next,
+                bit_offset: 0,
+                bit_size: 0,
+            },
+            BtfMember {
+                name: "tag",
+                type_id: arr,
+                bit_offset: 64,
+                bit_size: 0,
+            },
+        ];
+        let list = b.add_struct("list", 16, members).unwrap();
+        let values = vec![BtfEnumValue {
+            name: "NEG",
+            value: -1,
+        }];
+        b.add_enum("e", 4, values).unwrap();
+        let params = vec![BtfFuncParam {
+            name: "l",
+            type_id: b.add_const(list).unwrap(),
+        }];
+        let proto = b.add_func_proto(int, params).unwrap();
+        b.add_func("f", proto, BtfFuncKind::Global).unwrap();
+        let btf = b.build();
+        assert_eq!(btf.type_cnt(), 11);
+
+        let bytes = btf.to_bytes().unwrap();
+        let reloaded = Btf::load_from_bytes(&bytes, 8, scroll::LE).unwrap();
+        assert_eq!(reloaded.types(), btf.types());
+        assert_eq!(
+            reloaded.id_by_name(Some(BtfKind::Struct), "list"),
+            Some(list)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_type_ids() {
+        let mut b = BtfBuilder::new();
+        assert!(b.add_ptr(1).is_err());
+        let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+        assert!(b.add_typedef("self_ref", int + 1).is_err());
+        assert!(b.add_int("huge", 256, BtfIntEncoding::None).is_err());
+        assert_eq!(b.add_ptr(int).unwrap(), int + 1);
+    }
+}
@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::max;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Write};
 
-use lazy_static::lazy_static;
-use regex::RegexSet;
+#[cfg(not(feature = "no-regex"))]
+use regex::{Regex, RegexSet};
 
 use crate::types::*;
-use crate::{btf_error, BtfResult};
+use crate::{btf_error, BtfError, BtfResult};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 enum OrderState {
@@ -37,7 +42,17 @@ struct TypeState {
     order_state: OrderState,
     emit_state: EmitState,
     fwd_emitted: bool,
+    /// whether any other type references this one; anonymous types that are referenced are
+    /// defined inline, while unreferenced anonymous enums are defined on their own
+    referenced: bool,
+    /// anonymous struct/union emitted as a stand-alone `typedef` with a generated name, see
+    /// `CDumperCfg::anon_typedefs`
+    anon_typedef: bool,
+    /// already recorded in `CDumper::skipped`
+    skipped: bool,
     name: String,
+    /// enum value names resolved upfront, consumed by the first emission of enum
+    val_names: Vec<String>,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
@@ -46,56 +61,612 @@ enum NamedKind {
     Ident,
 }
 
-#[derive(Debug)]
+/// Capabilities of the C dialect generated code is going to be compiled with. Types that can't
+/// be expressed with the given capabilities are reported instead of being silently emitted as
+/// invalid C.
+#[derive(Debug, Copy, Clone)]
+pub struct CTarget {
+    /// Target compiler supports `__int128` (and thus 128-bit ints in general)
+    pub has_int128: bool,
+    /// Target compiler supports `_Bool` (C99 and later)
+    pub has_bool: bool,
+}
+
+impl Default for CTarget {
+    fn default() -> Self {
+        CTarget {
+            has_int128: true,
+            has_bool: true,
+        }
+    }
+}
+
+/// Indentation of nested definitions and struct/union members
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Indent {
+    /// one tab per nesting level
+    #[default]
+    Tabs,
+    /// given number of spaces per nesting level
+    Spaces(usize),
+}
+
+#[derive(Debug, Default)]
 pub struct CDumperCfg {
     pub verbose: bool,
     pub union_as_struct: bool,
+    pub target: CTarget,
+    /// Fail the dump instead of emitting `#error` for types not representable on target, as well
+    /// as for ints of non-standard width referenced other than as struct/union members (which
+    /// are emitted as bitfields), as they can only be approximated by wider C types
+    pub strict: bool,
+    /// Give anonymous struct/union members generated names (__anon_0, __anon_1, ...)
+    pub name_anon_members: bool,
+    /// Only emit types with IDs starting from this one. Types with lower IDs (e.g., base BTF
+    /// types program's own types are appended to) are assumed to be declared elsewhere and are
+    /// only referenced by name.
+    pub min_id: u32,
+    /// Wrap output in `extern "C" { ... }` when compiled as C++
+    pub extern_c: bool,
+    /// Annotate top-level struct definitions with cache line boundaries of this size (0 to
+    /// disable), pahole-style
+    pub cacheline_sz: u32,
+    /// Precede each struct/union definition with `/* fields: a, b, c */` comment listing member
+    /// names in order, for generating designated initializers
+    pub field_lists: bool,
+    /// Follow each struct/union member with `/* <byte offset> <byte size> */` comment, or
+    /// `/* bit <bit offset> <bit size> */` for bitfields, pahole-style
+    pub field_offsets: bool,
+    /// Stop after emitting this many top-level definitions (0 means no limit)
+    pub max_types: usize,
+    /// Mark each inlined anonymous struct/union/enum with a comment containing its BTF type ID
+    pub anon_type_ids: bool,
+    /// Resolve names of all types upfront, in type ID order, so that `___N` suffixes don't
+    /// depend on which types are dumped and in what order
+    pub stable_names: bool,
+    /// Wrap output in `#ifndef <guard>`/`#define <guard>` include guard
+    pub header_guard: Option<String>,
+    /// Include <stdbool.h> and/or <stdint.h> at the top, if BTF has bool ints or ints named as
+    /// fixed-width ones (e.g., `uint32_t`), so that output is self-contained
+    pub std_includes: bool,
+    /// Apply `preserve_access_index` attribute to all structs and unions, making field accesses
+    /// CO-RE-relocatable, unless BPF_NO_PRESERVE_ACCESS_INDEX is defined
+    pub preserve_access_index: bool,
+    /// Group dumped types under `/* from <file> */` comments by source file they likely come
+    /// from, as determined from .BTF.ext line info
+    pub group_by_file: bool,
+    /// Indentation of nested definitions and members, tabs by default
+    pub indent: Indent,
+    /// Emit anonymous structs/unions as `typedef struct { ... } __anon_<id>;` definitions and
+    /// refer to them by that name, instead of defining them inline. Anonymous members and
+    /// anonymous types defined by typedefs are still inlined, as they have to be.
+    pub anon_typedefs: bool,
+    /// Names to emit full-width ints with, instead of their own names, keyed on int's width in
+    /// bits and encoding, e.g., `(64, BtfIntEncoding::None)` -> `uint64_t`. Plain `char` is
+    /// always kept as is. <stdint.h> is included by prologue if any int is mapped to one of its
+    /// types. Empty by default, see `with_fixed_width_ints()`.
+    pub int_names: HashMap<(u32, BtfIntEncoding), String>,
+}
+
+impl CDumperCfg {
+    /// Settings producing output following conventions of vmlinux.h generated by libbpf's
+    /// btf_dump (e.g., `bpftool btf dump file /sys/kernel/btf/vmlinux format c`).
+    pub fn vmlinux_preset() -> CDumperCfg {
+        CDumperCfg {
+            header_guard: Some("__VMLINUX_H__".to_owned()),
+            preserve_access_index: true,
+            ..Default::default()
+        }
+    }
+
+    /// Maps ints to `<stdint.h>` fixed-width types of the same width and signedness (e.g., `long
+    /// unsigned int` to `uint64_t` for 64-bit targets), so that generated header means the same
+    /// on any target. Mapping can be further adjusted through `int_names`.
+    pub fn with_fixed_width_ints(mut self) -> CDumperCfg {
+        for bits in [8, 16, 32, 64] {
+            let signed = format!("int{}_t", bits);
+            let unsigned = format!("uint{}_t", bits);
+            self.int_names
+                .insert((bits, BtfIntEncoding::Signed), signed);
+            self.int_names
+                .insert((bits, BtfIntEncoding::None), unsigned);
+        }
+        self
+    }
+
+    /// Sets include guard derived from a given name, e.g., `vmlinux` or `vmlinux.h` both
+    /// result in `__VMLINUX_H__`.
+    pub fn with_header_guard(mut self, name: &str) -> CDumperCfg {
+        let mut guard: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        guard = guard.trim_matches('_').to_owned();
+        if let Some(stem) = guard.strip_suffix("_H") {
+            guard = stem.to_owned();
+        }
+        self.header_guard = Some(format!("__{}_H__", guard));
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SkipReason {
+    /// name matches names blacklist
+    Blacklisted,
+    /// BTF kind has no C type definition (func, var, datasec, decl tag)
+    UnsupportedKind,
+    /// type is part of unsatisfiable type cycle
+    Cycle,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::Blacklisted => write!(f, "blacklisted"),
+            SkipReason::UnsupportedKind => write!(f, "unsupported kind"),
+            SkipReason::Cycle => write!(f, "type cycle"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SkippedType {
+    pub id: u32,
+    pub reason: SkipReason,
+}
+
+/// Writer generated code goes to, which can be temporarily redirected into a buffer
+struct Sink<W: Write> {
+    inner: W,
+    capture: Option<Vec<u8>>,
+    /// blank line separating top-level blocks, written only once anything follows it
+    blank_pending: bool,
+}
+
+impl<W: Write> Sink<W> {
+    fn blank_line(&mut self) {
+        self.blank_pending = true;
+    }
+}
+
+impl<W: Write> Write for Sink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.capture {
+            Some(capture) => capture.write(buf),
+            None => {
+                if self.blank_pending && !buf.is_empty() {
+                    self.inner.write_all(b"\n")?;
+                    self.blank_pending = false;
+                }
+                self.inner.write(buf)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.capture {
+            Some(_) => Ok(()),
+            None => self.inner.flush(),
+        }
+    }
 }
 
-pub struct CDumper<'a> {
+/// Generates compilable C definitions of BTF types, writing them into `writer`.
+///
+/// Output is deterministic for given BTF, config and sequence of calls, and follows a fixed
+/// whitespace contract: each top-level block (type definition or forward declaration, group of
+/// var or func declarations, prologue/epilogue section, comment preceding group of types) ends
+/// with a newline and is separated from the next one by exactly one blank line. There are no
+/// blank lines before the first block and after the last one, so output ends with a single
+/// newline.
+pub struct CDumper<'a, W: Write> {
     btf: &'a Btf<'a>,
+    writer: Sink<W>,
     cfg: CDumperCfg,
     state: Vec<TypeState>,
-    names: HashMap<(NamedKind, &'a str), u32>,
+    names: HashMap<(NamedKind, Cow<'a, str>), u32>,
+    name_map: HashMap<u32, String>,
+    names_blacklist: NamesSet,
+    skipped: Vec<SkippedType>,
+    /// number of top-level definitions (including fwd declarations) emitted so far
+    def_cnt: usize,
+    truncated: bool,
+}
+
+impl<'a> CDumper<'a, Vec<u8>> {
+    /// Dumps all types matching `filter` (along with their dependencies), surrounded by
+    /// prologue and epilogue, and returns generated C code as a string.
+    pub fn dump_to_string(
+        btf: &'a Btf<'a>,
+        filter: Box<dyn Fn(u32, &'a BtfType<'a>) -> bool>,
+        cfg: CDumperCfg,
+    ) -> BtfResult<String> {
+        let mut dumper = CDumper::try_new(btf, Vec::new(), cfg)?;
+        dumper.emit_prologue()?;
+        dumper.dump_types(filter)?;
+        dumper.emit_epilogue()?;
+        Ok(String::from_utf8(dumper.into_writer())?)
+    }
+
+    /// Filter for `dump_types()` matching types of any of given kinds. Like with any other
+    /// filter, types with blacklisted names are still skipped.
+    pub fn filter_by_kinds(kinds: &[BtfKind]) -> Box<dyn Fn(u32, &'a BtfType<'a>) -> bool> {
+        let kinds = kinds.to_vec();
+        Box::new(move |_: u32, t: &BtfType| kinds.contains(&t.kind()))
+    }
+
+    /// Filter for `dump_types()` matching types with names matching given regex. With
+    /// `no-regex` feature only literal patterns are supported (matching the same names a regex
+    /// would), and patterns with regex metacharacters are rejected. Like with any other filter,
+    /// types with blacklisted names are still skipped.
+    pub fn filter_by_name_regex(re: &str) -> BtfResult<Box<dyn Fn(u32, &'a BtfType<'a>) -> bool>> {
+        #[cfg(not(feature = "no-regex"))]
+        {
+            let re = Regex::new(re)?;
+            Ok(Box::new(move |_: u32, t: &BtfType| re.is_match(t.name())))
+        }
+        #[cfg(feature = "no-regex")]
+        {
+            if let Some(c) = re.chars().find(|c| "\\.+*?()|[]{}^$#&-~".contains(*c)) {
+                return btf_error(format!(
+                    "Regex metacharacter '{}' in name pattern '{}' is not supported without regex",
+                    c, re
+                ));
+            }
+            let lit = re.to_owned();
+            Ok(Box::new(move |_: u32, t: &BtfType| t.name().contains(&lit)))
+        }
+    }
 }
 
-impl<'a> CDumper<'a> {
-    pub fn new(btf: &'a Btf<'a>, cfg: CDumperCfg) -> CDumper<'a> {
+impl<'a, W: Write> CDumper<'a, W> {
+    /// Creates dumper of given BTF, assuming all type references in it are valid, as is the case
+    /// for BTF loaded or built by this crate; see `try_new()` otherwise.
+    pub fn new(btf: &'a Btf<'a>, writer: W, cfg: CDumperCfg) -> CDumper<'a, W> {
         let mut dumper = CDumper {
             btf: btf,
+            writer: Sink {
+                inner: writer,
+                capture: None,
+                blank_pending: false,
+            },
             cfg: cfg,
             state: Vec::new(),
             names: HashMap::new(),
+            name_map: HashMap::new(),
+            names_blacklist: NamesSet::new(BLACKLISTED_NAMES).expect("invalid blacklist regexes"),
+            skipped: Vec::new(),
+            def_cnt: 0,
+            truncated: false,
         };
+        dumper.init_state();
         dumper
-            .state
-            .resize_with(btf.type_cnt() as usize, Default::default);
-        dumper
+    }
+
+    /// Same as `new()`, but first checks that no type references a type ID that is out of
+    /// bounds, failing otherwise. Once created, dumper doesn't panic on any type ID passed to
+    /// it, reporting out of bounds ones as errors instead.
+    pub fn try_new(btf: &'a Btf<'a>, writer: W, cfg: CDumperCfg) -> BtfResult<CDumper<'a, W>> {
+        btf.validate_type_ids(1)?;
+        Ok(CDumper::new(btf, writer, cfg))
+    }
+
+    /// Forgets everything emitted so far, so that following dump_*() calls produce output
+    /// independent of previous ones, as if done by a newly created dumper (with the same
+    /// writer, config and blacklist). Without reset, successive dump_*() calls share state:
+    /// types already emitted (e.g., as dependencies of functions by `dump_funcs()`) are not
+    /// emitted again and name conflicts are resolved across all of them, which is what's
+    /// needed when producing a single header in several passes. Reset is needed when each
+    /// pass is meant to be a stand-alone header. It also clears `skipped()`, `truncated()`
+    /// and `name_map()`, and restarts `max_types` counting.
+    pub fn reset(&mut self) {
+        self.state.clear();
+        self.names.clear();
+        self.name_map.clear();
+        self.skipped.clear();
+        self.def_cnt = 0;
+        self.truncated = false;
+        self.writer.blank_pending = false;
+        self.init_state();
+    }
+
+    fn init_state(&mut self) {
+        self.state
+            .resize_with(self.btf.type_cnt() as usize, Default::default);
+        for (_, t) in self.btf.iter() {
+            t.visit_type_ids(|id| self.state[id as usize].referenced = true);
+        }
+        if self.cfg.anon_typedefs {
+            self.mark_anon_typedefs();
+        }
+        if self.cfg.stable_names {
+            self.resolve_all_names();
+        }
+    }
+
+    /// Replaces default names blacklist (`BLACKLISTED_NAMES`) with given regexes (or exact
+    /// names, with `no-regex` feature). Types with names matching any of them are never
+    /// emitted, as if they were declared elsewhere.
+    pub fn set_name_blacklist(&mut self, patterns: &[&str]) -> BtfResult<()> {
+        self.names_blacklist = NamesSet::new(patterns)?;
+        Ok(())
+    }
+
+    /// Consumes dumper, returning the writer generated C code was written to
+    pub fn into_writer(self) -> W {
+        self.writer.inner
+    }
+
+    /// Emits everything that has to precede type definitions. Should be called once before any
+    /// of the dump_*() calls, if output is meant to be used as a stand-alone header.
+    pub fn emit_prologue(&mut self) -> io::Result<()> {
+        if let Some(guard) = &self.cfg.header_guard {
+            writeln!(self.writer, "#ifndef {}", guard)?;
+            writeln!(self.writer, "#define {}", guard)?;
+            self.writer.blank_line();
+        }
+        let ints = self
+            .btf
+            .iter_kind(BtfKind::Int)
+            .filter_map(|(_, t)| match t {
+                BtfType::Int(t) => Some(t),
+                _ => None,
+            });
+        let (mut need_bool, mut need_int) = (false, false);
+        for t in ints {
+            // ints mapped to fixed-width types always need them declared
+            let name = int_name(&self.cfg, t);
+            need_int |= is_stdint_name(name) && (self.cfg.std_includes || name != t.name);
+            need_bool |=
+                self.cfg.std_includes && (t.encoding == BtfIntEncoding::Bool || t.name == "bool");
+        }
+        if need_bool {
+            writeln!(self.writer, "#include <stdbool.h>")?;
+        }
+        if need_int {
+            writeln!(self.writer, "#include <stdint.h>")?;
+        }
+        if need_bool || need_int {
+            self.writer.blank_line();
+        }
+        if self.cfg.extern_c {
+            writeln!(self.writer, "#ifdef __cplusplus")?;
+            writeln!(self.writer, "extern \"C\" {{")?;
+            writeln!(self.writer, "#endif")?;
+            self.writer.blank_line();
+        }
+        if self.cfg.preserve_access_index {
+            writeln!(self.writer, "#ifndef BPF_NO_PRESERVE_ACCESS_INDEX")?;
+            writeln!(
+                self.writer,
+                "#pragma clang attribute push (__attribute__((preserve_access_index)), \
+                 apply_to = record)"
+            )?;
+            writeln!(self.writer, "#endif")?;
+            self.writer.blank_line();
+        }
+        Ok(())
+    }
+
+    /// Emits everything that has to follow type definitions, closing whatever was opened by
+    /// emit_prologue().
+    pub fn emit_epilogue(&mut self) -> io::Result<()> {
+        if self.cfg.preserve_access_index {
+            writeln!(self.writer, "#ifndef BPF_NO_PRESERVE_ACCESS_INDEX")?;
+            writeln!(self.writer, "#pragma clang attribute pop")?;
+            writeln!(self.writer, "#endif")?;
+            self.writer.blank_line();
+        }
+        if self.cfg.extern_c {
+            writeln!(self.writer, "#ifdef __cplusplus")?;
+            writeln!(self.writer, "}}")?;
+            writeln!(self.writer, "#endif")?;
+            self.writer.blank_line();
+        }
+        if let Some(guard) = &self.cfg.header_guard {
+            writeln!(self.writer, "#endif /* {} */", guard)?;
+        }
+        Ok(())
     }
 
     pub fn dump_types(
         &mut self,
         filter: Box<dyn Fn(u32, &'a BtfType<'a>) -> bool>,
     ) -> BtfResult<()> {
-        for id in 1..self.btf.type_cnt() {
+        let ids: Vec<u32> = (max(1, self.cfg.min_id)..self.btf.type_cnt()).collect();
+        if !self.cfg.group_by_file {
+            return self.dump_type_ids(&ids, &filter);
+        }
+        // types are grouped by file, in order of file names, with unattributed ones last;
+        // dependencies from other files are still emitted right before the type using them
+        let files = self.btf.type_source_files();
+        let mut groups: BTreeMap<Option<&str>, Vec<u32>> = BTreeMap::new();
+        for id in ids {
+            let file = files.get(&id).copied();
+            groups.entry(file).or_default().push(id);
+        }
+        let unknown = groups.remove(&None);
+        let groups = groups.into_iter().chain(unknown.map(|ids| (None, ids)));
+        for (file, ids) in groups {
+            if !ids.iter().any(|&id| self.is_pending_def(id, &filter)) {
+                continue;
+            }
+            writeln!(self.writer, "/* from {} */", file.unwrap_or("unknown"))?;
+            self.writer.blank_line();
+            self.dump_type_ids(&ids, &filter)?;
+            if self.truncated {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether dumping given type is going to emit its definition
+    fn is_pending_def(&self, id: u32, filter: &dyn Fn(u32, &'a BtfType<'a>) -> bool) -> bool {
+        let bt = self.btf.type_by_id(id);
+        let definable = match bt {
+            BtfType::Struct(_)
+            | BtfType::Union(_)
+            | BtfType::Enum(_)
+            | BtfType::Enum64(_)
+            | BtfType::Fwd(_)
+            | BtfType::Typedef(_) => {
+                !bt.name().is_empty()
+                    || self.is_standalone_anon_enum(id)
+                    || self.is_anon_typedef(id)
+            }
+            _ => false,
+        };
+        definable && self.get_emit_state(id) != EmitState::Emitted && filter(id, bt)
+    }
+
+    fn dump_type_ids(
+        &mut self,
+        ids: &[u32],
+        filter: &dyn Fn(u32, &'a BtfType<'a>) -> bool,
+    ) -> BtfResult<()> {
+        for &id in ids {
             let bt = self.btf.type_by_id(id);
             if filter(id, bt) {
-                self.dump_type(id)?;
+                match bt {
+                    // these have no C type definition
+                    BtfType::Func(_)
+                    | BtfType::Var(_)
+                    | BtfType::Datasec(_)
+                    | BtfType::DeclTag(_) => self.record_skipped(id, SkipReason::UnsupportedKind),
+                    _ => self.dump_type(id)?,
+                }
+            }
+            if self.truncated {
+                writeln!(
+                    self.writer,
+                    "/* output truncated after {} type definitions */",
+                    self.def_cnt
+                )?;
+                self.writer.blank_line();
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits declarations of vars of each matching datasec, grouped under a comment with datasec
+    /// name. All the types used by vars are emitted upfront.
+    pub fn dump_datasecs(
+        &mut self,
+        filter: Box<dyn Fn(u32, &'a BtfType<'a>) -> bool>,
+    ) -> BtfResult<()> {
+        let mut secs = Vec::new();
+        for id in 1..self.btf.type_cnt() {
+            let bt = self.btf.type_by_id(id);
+            if let BtfType::Datasec(t) = bt {
+                if filter(id, bt) {
+                    let vars = self.btf.datasec_vars(t).into_iter().map(|(_, v)| v);
+                    secs.push((t.name, vars.collect()));
+                }
+            }
+        }
+        self.emit_var_decls(secs)
+    }
+
+    /// Same as `dump_datasecs()`, but emits declarations of individual vars matching filter,
+    /// still grouped by datasec they belong to. Global vars are declared `extern`, while static
+    /// ones are declared `static`, as they aren't visible outside of their object file.
+    pub fn dump_vars(
+        &mut self,
+        filter: Box<dyn Fn(u32, &'a BtfType<'a>) -> bool>,
+    ) -> BtfResult<()> {
+        let mut secs = Vec::new();
+        for (_, t) in self.btf.iter_kind(BtfKind::Datasec) {
+            if let BtfType::Datasec(t) = t {
+                let vars: Vec<_> = self
+                    .btf
+                    .datasec_vars(t)
+                    .into_iter()
+                    .filter(|&(id, _)| filter(id, self.btf.type_by_id(id)))
+                    .map(|(_, v)| v)
+                    .collect();
+                if !vars.is_empty() {
+                    secs.push((t.name, vars));
+                }
+            }
+        }
+        self.emit_var_decls(secs)
+    }
+
+    fn emit_var_decls(&mut self, secs: Vec<(&'a str, Vec<&'a BtfVar<'a>>)>) -> BtfResult<()> {
+        for (_, vars) in &secs {
+            for v in vars {
+                self.dump_type(v.type_id)?;
+            }
+        }
+        for (name, vars) in secs {
+            writeln!(self.writer, "/* section {} */", name)?;
+            for v in vars {
+                match v.kind {
+                    BtfVarKind::Static => write!(self.writer, "static ")?,
+                    BtfVarKind::GlobalAlloc | BtfVarKind::GlobalExtern => {
+                        write!(self.writer, "extern ")?
+                    }
+                }
+                self.emit_type_decl(v.type_id, v.name, 0)?;
+                writeln!(self.writer, ";")?;
+            }
+            self.writer.blank_line();
+        }
+        Ok(())
+    }
+
+    /// Emits extern prototypes of all funcs matching filter, e.g., to produce a header of kernel
+    /// function signatures. All the types used by prototypes are emitted upfront. Only the first
+    /// of funcs sharing the same name (e.g., static funcs from different compilation units) is
+    /// emitted, as C doesn't allow conflicting declarations.
+    pub fn dump_funcs(
+        &mut self,
+        filter: Box<dyn Fn(u32, &'a BtfType<'a>) -> bool>,
+    ) -> BtfResult<()> {
+        let mut seen = HashSet::new();
+        let mut funcs = Vec::new();
+        for (id, f, _) in self.btf.functions() {
+            if filter(id, self.btf.type_by_id(id)) && seen.insert(f.name) {
+                funcs.push(f);
             }
         }
+        if funcs.is_empty() {
+            return Ok(());
+        }
+        for f in &funcs {
+            self.dump_type(f.proto_type_id)?;
+        }
+        for f in funcs {
+            write!(self.writer, "extern ")?;
+            self.emit_type_decl(f.proto_type_id, f.name, 0)?;
+            writeln!(self.writer, ";")?;
+        }
+        self.writer.blank_line();
         Ok(())
     }
 
     pub fn dump_type(&mut self, id: u32) -> BtfResult<()> {
+        if id >= self.btf.type_cnt() {
+            return btf_error(format!("Type ID {} is out of bounds", id));
+        }
         let mut order = Vec::new();
         if self.cfg.verbose {
-            println!("===================================================");
-            println!("ORDERING id: {}, type: {}", id, self.btf.type_by_id(id));
+            eprintln!("===================================================");
+            eprintln!("ORDERING id: {}, type: {}", id, self.btf.type_by_id(id));
         }
         self.order_type(id, false, &mut order)?;
         if self.cfg.verbose {
             for (i, &id) in order.iter().enumerate() {
-                println!("ORDER #{} id: {}, type: {}", i, id, self.btf.type_by_id(id));
+                eprintln!("ORDER #{} id: {}, type: {}", i, id, self.btf.type_by_id(id));
             }
         }
         // emit struct/union and fwds required by them in correct order
@@ -105,9 +676,25 @@ impl<'a> CDumper<'a> {
         Ok(())
     }
 
+    /// Emits given type along with the types its definition depends on, and nothing else, e.g.,
+    /// to get a minimal compilable header for a single struct. Dependencies are ordered by the
+    /// same rules as with `dump_types()`, so types only referenced through pointers are just
+    /// forward-declared. Each type is still emitted at most once across calls, so closures of
+    /// multiple types can be combined into one header.
+    pub fn dump_type_closure(&mut self, root_id: u32) -> BtfResult<()> {
+        if root_id >= self.btf.type_cnt() {
+            return btf_error(format!("Type ID {} is out of bounds", root_id));
+        }
+        self.dump_type_ids(&[root_id], &|_, _| true)
+    }
+
     fn order_type(&mut self, id: u32, has_ptr: bool, order: &mut Vec<u32>) -> BtfResult<bool> {
+        if self.is_assumed_declared(id) {
+            // defined elsewhere, so safe to reference in any way
+            return Ok(true);
+        }
         if self.cfg.verbose && self.get_order_state(id) != OrderState::Ordered {
-            println!(
+            eprintln!(
                 "ORDER TYPE id:{}, has_ptr:{}, type:{}, order_state:{:?}",
                 id,
                 has_ptr,
@@ -129,11 +716,8 @@ impl<'a> CDumper<'a> {
                     return Ok(false);
                 }
                 _ => {
-                    return btf_error(format!(
-                        "Unsatisfiable type cycle, id: {}, type: {}",
-                        id,
-                        self.btf.type_by_id(id)
-                    ));
+                    self.record_skipped(id, SkipReason::Cycle);
+                    return Err(Box::new(BtfError::TypeCycle { id }));
                 }
             },
             // return true, letting typedefs know that it's ok to be emitted
@@ -174,7 +758,7 @@ impl<'a> CDumper<'a> {
                         self.order_type(m.type_id, false, order)?;
                     }
                     // no need to explicitly order anonymous embedded struct
-                    if !t.name.is_empty() {
+                    if !t.name.is_empty() || self.is_anon_typedef(id) {
                         order.push(id);
                     }
 
@@ -183,8 +767,8 @@ impl<'a> CDumper<'a> {
                     return Ok(true);
                 }
             }
-            BtfType::Enum(t) => {
-                if !t.name.is_empty() {
+            BtfType::Enum(t) | BtfType::Enum64(t) => {
+                if !t.name.is_empty() || self.is_standalone_anon_enum(id) {
                     order.push(id);
                 }
                 self.set_order_state(id, OrderState::Ordered);
@@ -213,9 +797,12 @@ impl<'a> CDumper<'a> {
     }
 
     fn emit_type(&mut self, id: u32, cont_id: u32) -> BtfResult<()> {
+        if self.is_assumed_declared(id) {
+            return Ok(());
+        }
         let top_level_def = cont_id == 0;
         if self.cfg.verbose {
-            println!(
+            eprintln!(
                 "EMIT_TYPE id: {}, cont_id: {}, is_def: {}, state: {:?}, type: {}",
                 id,
                 cont_id,
@@ -233,19 +820,23 @@ impl<'a> CDumper<'a> {
                 match self.btf.type_by_id(id) {
                     BtfType::Struct(t) | BtfType::Union(t) => {
                         // fwd was already emitted or no need for fwd declare if we are referencing
-                        // a struct/union we are part of
-                        if id == cont_id {
+                        // a struct/union we are part of; typedef'ed anonymous ones can't be
+                        // forward-declared anyway
+                        if id == cont_id || self.is_anon_typedef(id) {
                             return Ok(());
                         }
                         if t.name.is_empty() {
                             return btf_error(format!(
-                                "anonymous struct loop, id: {}, type: {}",
+                                "anonymous struct loop at [{}] {}",
                                 id,
-                                self.btf.type_by_id(id)
+                                self.btf.full_name(id)
                             ));
                         }
-                        if self.emit_composite_fwd(id, t) {
-                            println!(";\n");
+                        if self.limit_reached() {
+                            return Ok(());
+                        }
+                        if self.emit_composite_fwd(id, t)? {
+                            self.end_def()?;
                         }
                         self.set_fwd_emitted(id, true);
                         return Ok(());
@@ -253,8 +844,11 @@ impl<'a> CDumper<'a> {
                     BtfType::Typedef(t) => {
                         // for typedef fwd_emitted means typedef definition was emitted, but it can
                         // be used only for "weak" references through pointer only
-                        if self.emit_typedef_def(id, t, 0) {
-                            println!(";\n");
+                        if self.limit_reached() {
+                            return Ok(());
+                        }
+                        if self.emit_typedef_def(id, t, 0)? {
+                            self.end_def()?;
                         }
                         self.set_fwd_emitted(id, true);
                         return Ok(());
@@ -265,17 +859,41 @@ impl<'a> CDumper<'a> {
             EmitState::Emitted => return Ok(()),
         }
 
-        if top_level_def && self.btf.type_by_id(id).name().is_empty() {
+        if top_level_def
+            && self.btf.type_by_id(id).name().is_empty()
+            && !self.is_standalone_anon_enum(id)
+            && !self.is_anon_typedef(id)
+        {
             return btf_error(format!(
-                "unexpected nameless definition, id: {}, type: {}",
+                "unexpected nameless definition of [{}] {}",
                 id,
-                self.btf.type_by_id(id)
+                self.btf.full_name(id)
             ));
         }
 
         match self.btf.type_by_id(id) {
             BtfType::Func(_) | BtfType::Var(_) | BtfType::Datasec(_) | BtfType::DeclTag(_) => {}
-            BtfType::Void | BtfType::Int(_) | BtfType::Float(_) => {}
+            BtfType::Void | BtfType::Float(_) => {}
+            BtfType::Int(t) => {
+                if let Some(reason) = self.unsupported_int_reason(t) {
+                    if self.cfg.strict {
+                        return btf_error(format!(
+                            "cannot represent [{}] {}: {}",
+                            id,
+                            self.btf.full_name(id),
+                            reason
+                        ));
+                    }
+                    // preprocessor directive can't go in the middle of a declaration, so it's
+                    // emitted before the first top-level definition using the type
+                    writeln!(
+                        self.writer,
+                        "#error \"cannot represent type {}: {}\"",
+                        t.name, reason
+                    )?;
+                }
+                self.set_emit_state(id, EmitState::Emitted);
+            }
             BtfType::Volatile(t) => self.emit_type(t.type_id, cont_id)?,
             BtfType::Const(t) => self.emit_type(t.type_id, cont_id)?,
             BtfType::Restrict(t) => self.emit_type(t.type_id, cont_id)?,
@@ -289,37 +907,74 @@ impl<'a> CDumper<'a> {
                 }
             }
             BtfType::Struct(t) | BtfType::Union(t) => {
+                let anon_typedef = self.is_anon_typedef(id);
+                if anon_typedef && !top_level_def {
+                    // already defined, as it's ordered before any type referencing it
+                    return Ok(());
+                }
                 self.set_emit_state(id, EmitState::Emitting);
                 if top_level_def || t.name.is_empty() {
                     // top-level struct definition or embedded anonymous struct, ensure all field
                     // types have their fwds declared
+                    let inline = t.name.is_empty() && !anon_typedef;
                     for m in &t.members {
-                        self.emit_type(m.type_id, if t.name.is_empty() { cont_id } else { id })?;
+                        // members of non-standard width ints are emitted as bitfields, so don't
+                        // need ints themselves to be representable
+                        if !self.btf.is_zero_width_bitfield(m)
+                            && self.odd_width_int(m.type_id).is_some()
+                        {
+                            continue;
+                        }
+                        self.emit_type(m.type_id, if inline { cont_id } else { id })?;
                     }
                 } else if !self.get_fwd_emitted(id) && id != cont_id {
-                    if self.emit_composite_fwd(id, t) {
-                        println!(";\n");
+                    if self.limit_reached() {
+                        return Ok(());
+                    }
+                    if self.emit_composite_fwd(id, t)? {
+                        self.end_def()?;
                     }
                     self.set_fwd_emitted(id, true);
                 }
                 if top_level_def {
-                    self.emit_composite_def(id, t, 0);
-                    println!(";\n");
+                    if self.limit_reached() {
+                        return Ok(());
+                    }
+                    if self.cfg.field_lists {
+                        self.emit_field_list(t)?;
+                    }
+                    if anon_typedef {
+                        write!(self.writer, "typedef ")?;
+                        self.emit_composite_def(id, t, 0)?;
+                        let name = self.anon_typedef_name(id);
+                        write!(self.writer, " {}", name)?;
+                        self.end_def()?;
+                    } else if self.emit_composite_def(id, t, 0)? {
+                        self.end_def()?;
+                    }
                     self.set_emit_state(id, EmitState::Emitted);
                 } else {
                     self.set_emit_state(id, EmitState::NotEmitted);
                 }
             }
-            BtfType::Enum(t) => {
+            BtfType::Enum(t) | BtfType::Enum64(t) => {
                 if top_level_def {
-                    self.emit_enum_def(id, t, 0);
-                    println!(";\n");
+                    if self.limit_reached() {
+                        return Ok(());
+                    }
+                    if self.emit_enum_def(id, t, 0)? {
+                        self.end_def()?;
+                    }
                 }
                 self.set_emit_state(id, EmitState::Emitted);
             }
             BtfType::Fwd(t) => {
-                self.emit_fwd_def(id, t);
-                println!(";\n");
+                if self.limit_reached() {
+                    return Ok(());
+                }
+                if self.emit_fwd_def(id, t)? {
+                    self.end_def()?;
+                }
                 self.set_emit_state(id, EmitState::Emitted);
             }
             BtfType::Typedef(t) => {
@@ -327,8 +982,11 @@ impl<'a> CDumper<'a> {
                 self.emit_type(t.type_id, id)?;
                 if !self.get_fwd_emitted(id) {
                     // emit typedef right now, if someone depends on it "weakly" (though pointer)
-                    if self.emit_typedef_def(id, t, 0) {
-                        println!(";\n");
+                    if self.limit_reached() {
+                        return Ok(());
+                    }
+                    if self.emit_typedef_def(id, t, 0)? {
+                        self.end_def()?;
                     }
                     self.set_fwd_emitted(id, true);
                 }
@@ -338,6 +996,48 @@ impl<'a> CDumper<'a> {
         Ok(())
     }
 
+    /// Final names (with `___N` suffixes, if any) of named types resolved so far, by type ID.
+    /// Populated as types are emitted, or upfront with `stable_names`.
+    pub fn name_map(&self) -> &HashMap<u32, String> {
+        &self.name_map
+    }
+
+    /// Whether some of the definitions weren't emitted due to `max_types` limit
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    fn limit_reached(&mut self) -> bool {
+        if self.cfg.max_types != 0 && self.def_cnt >= self.cfg.max_types {
+            self.truncated = true;
+        }
+        self.truncated
+    }
+
+    fn end_def(&mut self) -> io::Result<()> {
+        writeln!(self.writer, ";")?;
+        self.writer.blank_line();
+        self.def_cnt += 1;
+        Ok(())
+    }
+
+    /// Types that were requested to be dumped, but were not emitted, in the order of discovery.
+    pub fn skipped(&self) -> &[SkippedType] {
+        &self.skipped
+    }
+
+    fn record_skipped(&mut self, id: u32, reason: SkipReason) {
+        let state = &mut self.state[id as usize];
+        if !state.skipped {
+            state.skipped = true;
+            self.skipped.push(SkippedType { id, reason });
+        }
+    }
+
+    fn is_assumed_declared(&self, id: u32) -> bool {
+        id != 0 && id < self.cfg.min_id
+    }
+
     fn get_fwd_emitted(&self, id: u32) -> bool {
         self.state[id as usize].fwd_emitted
     }
@@ -362,9 +1062,10 @@ impl<'a> CDumper<'a> {
         self.state[id as usize].emit_state = state;
     }
 
-    fn emit_composite_fwd(&mut self, id: u32, t: &'a BtfComposite) -> bool {
-        if NAMES_BLACKLIST.is_match(&t.name) {
-            return false;
+    fn emit_composite_fwd(&mut self, id: u32, t: &'a BtfComposite) -> io::Result<bool> {
+        if self.names_blacklist.is_match(t.name) {
+            self.record_skipped(id, SkipReason::Blacklisted);
+            return Ok(false);
         }
         let keyword = if !t.is_struct && self.cfg.union_as_struct {
             "struct /*union*/"
@@ -373,17 +1074,15 @@ impl<'a> CDumper<'a> {
         } else {
             "union"
         };
-        print!(
-            "{} {}",
-            keyword,
-            self.resolve_type_name(NamedKind::Type, id, t.name)
-        );
-        return true;
+        let name = self.resolve_type_name(NamedKind::Type, id, t.name);
+        write!(self.writer, "{} {}", keyword, name)?;
+        Ok(true)
     }
 
-    fn emit_composite_def(&mut self, id: u32, t: &'a BtfComposite, lvl: usize) {
-        if NAMES_BLACKLIST.is_match(&t.name) {
-            return;
+    fn emit_composite_def(&mut self, id: u32, t: &'a BtfComposite, lvl: usize) -> io::Result<bool> {
+        if self.names_blacklist.is_match(t.name) {
+            self.record_skipped(id, SkipReason::Blacklisted);
+            return Ok(false);
         }
         let keyword = if !t.is_struct && self.cfg.union_as_struct {
             "struct /*union*/"
@@ -392,80 +1091,204 @@ impl<'a> CDumper<'a> {
         } else {
             "union"
         };
-        let packed = self.is_struct_packed(id, t);
+        let packed = self.btf.is_struct_packed(id);
+        let over_align = self.btf.get_struct_over_align(id);
         let name = self.resolve_type_name(NamedKind::Type, id, t.name);
-        print!("{}{}{} {{", keyword, sep(&name), name);
+        write!(self.writer, "{}{}{} {{", keyword, sep(&name), name)?;
         let mut offset = 0;
-        for m in &t.members {
-            self.emit_bit_padding(offset, m, packed, lvl + 1);
+        let mut anon_cnt = 0;
+        // nested anonymous structs have member offsets relative to themselves, so annotate only
+        // top-level definitions
+        let lines = if self.cfg.cacheline_sz > 0 && t.is_struct && lvl == 0 {
+            self.btf.cacheline_report(id, self.cfg.cacheline_sz).ok()
+        } else {
+            None
+        };
+        let mut cur_line = 0;
+        for (i, m) in t.members.iter().enumerate() {
+            let zero_width = self.btf.is_zero_width_bitfield(m);
+            // int of non-standard width can't be referenced by its name as is, but a bitfield of
+            // the same width has the same layout
+            let odd_int = if !zero_width {
+                self.odd_width_int(m.type_id)
+            } else {
+                None
+            };
+            let is_bitfield = m.bit_size != 0 || odd_int.is_some() || zero_width;
+            // value of int with non-zero bit offset starts that many bits after member itself
+            let bit_offset = match (odd_int, m.bit_size) {
+                (Some(int), 0) => m.bit_offset + int.offset,
+                _ => m.bit_offset,
+            };
+            self.emit_bit_padding(offset, bit_offset, m, is_bitfield, packed, lvl + 1)?;
+            let line = lines.as_ref().map(|lines| &lines[i]);
+            if let Some(line) = line {
+                if line.first_line > cur_line {
+                    cur_line = line.first_line;
+                    write!(
+                        self.writer,
+                        "\n{}/* --- cacheline {} boundary ({} bytes) --- */",
+                        pfx(self.cfg.indent, lvl + 1),
+                        cur_line,
+                        cur_line * self.cfg.cacheline_sz
+                    )?;
+                }
+            }
 
-            print!("\n{}", pfx(lvl + 1));
-            self.emit_type_decl(m.type_id, &m.name, lvl + 1);
+            write!(self.writer, "\n{}", pfx(self.cfg.indent, lvl + 1))?;
+            if self.cfg.name_anon_members && self.is_anon_composite_member(m) {
+                let fname = Self::gen_anon_member_name(t, &mut anon_cnt);
+                self.emit_type_decl(m.type_id, &fname, lvl + 1)?;
+            } else if let Some(int) = odd_int {
+                let base = bitfield_base_name(int);
+                write!(self.writer, "{}{}{}", base, sep(m.name), m.name)?;
+            } else {
+                self.emit_type_decl(m.type_id, m.name, lvl + 1)?;
+            }
 
-            if m.bit_size == 0 {
-                offset = m.bit_offset + self.btf.get_size_of(m.type_id) * 8;
+            if zero_width {
+                write!(self.writer, ": 0")?;
+                offset = m.bit_offset;
+            } else if let (Some(int), 0) = (odd_int, m.bit_size) {
+                write!(self.writer, ": {}", int.bits)?;
+                offset = bit_offset + int.bits;
+            } else if m.bit_size == 0 {
+                let sz = self
+                    .btf
+                    .get_size_of(m.type_id)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+                offset = m.bit_offset.saturating_add(sz.saturating_mul(8));
             } else {
-                print!(": {}", m.bit_size);
+                write!(self.writer, ": {}", m.bit_size)?;
                 offset = m.bit_offset + m.bit_size as u32;
             }
-            print!(";");
+            write!(self.writer, ";")?;
+            if self.cfg.field_offsets {
+                if is_bitfield {
+                    let bits = offset - bit_offset;
+                    write!(self.writer, " /* bit {} {} */", bit_offset, bits)?;
+                } else {
+                    let sz = (offset - m.bit_offset) / 8;
+                    write!(self.writer, " /* {} {} */", m.bit_offset / 8, sz)?;
+                }
+            }
+            if let Some(line) = line {
+                if line.straddles() {
+                    write!(self.writer, " /* straddles cacheline {} */", line.last_line)?;
+                    cur_line = line.last_line;
+                }
+            }
         }
         if !t.members.is_empty() {
-            print!("\n");
+            writeln!(self.writer)?;
+        }
+        write!(self.writer, "{}}}", pfx(self.cfg.indent, lvl))?;
+        match (packed, over_align) {
+            (true, Some(align)) => {
+                write!(self.writer, " __attribute__((packed, aligned({})))", align)?
+            }
+            (true, None) => write!(self.writer, " __attribute__((packed))")?,
+            (false, Some(align)) => write!(self.writer, " __attribute__((aligned({})))", align)?,
+            (false, None) => {}
+        }
+        Ok(true)
+    }
+
+    fn emit_field_list(&mut self, t: &BtfComposite) -> io::Result<()> {
+        let mut names = Vec::new();
+        self.collect_field_names(t, &mut names);
+        writeln!(self.writer, "/* fields: {} */", names.join(", "))
+    }
+
+    fn collect_field_names(&self, t: &BtfComposite, names: &mut Vec<String>) {
+        let mut anon_cnt = 0;
+        for m in &t.members {
+            if !self.is_anon_composite_member(m) {
+                if !m.name.is_empty() {
+                    names.push(m.name.to_string());
+                }
+            } else if self.cfg.name_anon_members {
+                names.push(Self::gen_anon_member_name(t, &mut anon_cnt));
+            } else if let BtfType::Struct(t) | BtfType::Union(t) =
+                self.btf.type_by_id(self.btf.skip_mods(m.type_id))
+            {
+                // fields of anonymous struct/union are initialized as if they were our own
+                self.collect_field_names(t, names);
+            }
         }
-        print!("{}}}", pfx(lvl));
-        if packed {
-            print!(" __attribute__((packed))");
+    }
+
+    fn emit_anon_type_id(&mut self, kind: &str, id: u32) -> io::Result<()> {
+        if self.cfg.anon_type_ids {
+            write!(self.writer, " /* anon {} @ btf id {} */", kind, id)?;
         }
+        Ok(())
     }
 
-    fn is_struct_packed(&self, id: u32, t: &BtfComposite) -> bool {
-        if !t.is_struct {
+    fn is_anon_composite_member(&self, m: &BtfMember) -> bool {
+        if !m.name.is_empty() {
             return false;
         }
-        // size of a struct has to be a multiple of its alignment
-        if t.sz % self.btf.get_align_of(id) != 0 {
-            return true;
+        match self.btf.type_by_id(self.btf.skip_mods(m.type_id)) {
+            BtfType::Struct(t) | BtfType::Union(t) => t.name.is_empty(),
+            _ => false,
         }
-        // all the non-bitfield fields have to be naturally aligned
-        for m in &t.members {
-            if m.bit_size == 0 && m.bit_offset % (self.btf.get_align_of(m.type_id) * 8) != 0 {
-                return true;
+    }
+
+    fn gen_anon_member_name(t: &BtfComposite, anon_cnt: &mut u32) -> String {
+        loop {
+            let name = format!("__anon_{}", anon_cnt);
+            *anon_cnt += 1;
+            // don't clash with any of the actual field names
+            if t.members.iter().all(|m| m.name != name) {
+                return name;
             }
         }
-        // even if original struct was marked as packed, we haven't detected any misalignment, so
-        // there is no effect of packedness for given struct
-        return false;
     }
 
-    fn emit_bit_padding(&self, offset: u32, m: &BtfMember, packed: bool, lvl: usize) {
-        if offset >= m.bit_offset {
-            return;
+    fn emit_bit_padding(
+        &mut self,
+        offset: u32,
+        bit_offset: u32,
+        m: &BtfMember,
+        is_bitfield: bool,
+        packed: bool,
+        lvl: usize,
+    ) -> io::Result<()> {
+        if offset >= bit_offset {
+            return Ok(());
         }
-        let mut bit_diff = m.bit_offset - offset;
+        let mut bit_diff = bit_offset - offset;
         let align = if packed {
             1
         } else {
             self.btf.get_align_of(m.type_id)
         };
-        if m.bit_size == 0 && bit_diff < align * 8 {
+        if !is_bitfield && bit_diff < align * 8 {
             // natural padding will take care of a gap
-            return;
+            return Ok(());
         }
         let ptr_sz_bits = self.btf.ptr_sz() * 8;
         while bit_diff > 0 {
             let (pad_type, pad_bits) = if ptr_sz_bits > 32 && bit_diff > 32 {
-                ("long", CDumper::chip_away_bits(bit_diff, ptr_sz_bits))
+                ("long", Self::chip_away_bits(bit_diff, ptr_sz_bits))
             } else if bit_diff > 16 {
-                ("int", CDumper::chip_away_bits(bit_diff, 32))
+                ("int", Self::chip_away_bits(bit_diff, 32))
             } else if bit_diff > 8 {
-                ("short", CDumper::chip_away_bits(bit_diff, 16))
+                ("short", Self::chip_away_bits(bit_diff, 16))
             } else {
-                ("char", CDumper::chip_away_bits(bit_diff, 8))
+                ("char", Self::chip_away_bits(bit_diff, 8))
             };
             bit_diff -= pad_bits;
-            print!("\n{}{}: {};", pfx(lvl), pad_type, pad_bits);
+            write!(
+                self.writer,
+                "\n{}{}: {};",
+                pfx(self.cfg.indent, lvl),
+                pad_type,
+                pad_bits
+            )?;
         }
+        Ok(())
     }
 
     fn chip_away_bits(total: u32, at_most: u32) -> u32 {
@@ -476,52 +1299,122 @@ impl<'a> CDumper<'a> {
         }
     }
 
-    fn emit_enum_def(&mut self, id: u32, t: &'a BtfEnum, lvl: usize) {
-        if NAMES_BLACKLIST.is_match(&t.name) {
-            return;
+    fn emit_enum_def(&mut self, id: u32, t: &'a BtfEnum, lvl: usize) -> io::Result<bool> {
+        if self.names_blacklist.is_match(t.name) {
+            self.record_skipped(id, SkipReason::Blacklisted);
+            return Ok(false);
         }
         let name = self.resolve_type_name(NamedKind::Type, id, t.name);
         if t.values.is_empty() {
             // enum fwd
-            print!("enum{}{}", sep(&name), name);
+            write!(self.writer, "enum{}{}", sep(&name), name)?;
         } else {
-            print!("enum{}{} {{", sep(&name), name);
-            for v in &t.values {
-                let val_uniq_name = self.resolve_name(NamedKind::Ident, &v.name);
-                print!("\n{}{} = {},", pfx(lvl + 1), &val_uniq_name, v.value);
+            write!(self.writer, "enum{}{} {{", sep(&name), name)?;
+            let mut val_names = std::mem::take(&mut self.state[id as usize].val_names);
+            for (i, v) in t.values.iter().enumerate() {
+                let val_uniq_name = match val_names.get_mut(i) {
+                    Some(name) => std::mem::take(name),
+                    None => self.resolve_name(NamedKind::Ident, v.name),
+                };
+                let val = t.value_of(v);
+                // values beyond int range need a suffix to be of a wide enough type; the most
+                // negative one can't be written as a literal, as its negation doesn't fit
+                let val = if val == i64::MIN as i128 {
+                    "(-9223372036854775807LL - 1)".to_string()
+                } else if i32::try_from(val).is_ok() {
+                    val.to_string()
+                } else if t.is_signed || val < 0 {
+                    format!("{}LL", val)
+                } else {
+                    format!("{}ULL", val)
+                };
+                write!(
+                    self.writer,
+                    "\n{}{} = {},",
+                    pfx(self.cfg.indent, lvl + 1),
+                    &val_uniq_name,
+                    val
+                )?;
+            }
+            write!(self.writer, "\n{}}}", pfx(self.cfg.indent, lvl))?;
+            // C enums are int-sized by default, so 1- and 2-byte enums (e.g., `enum ... : u8`
+            // or __packed ones in kernel) are forced to their exact size; 8-byte enum with all
+            // values fitting into 32 bits would be int-sized as well, unless forced to be
+            // word-sized
+            match t.sz {
+                1 => write!(self.writer, " __attribute__((mode(QI)))")?,
+                2 => write!(self.writer, " __attribute__((mode(HI)))")?,
+                8 if self.btf.ptr_sz() == 8 && Self::fits_32_bits(t) => {
+                    write!(self.writer, " __attribute__((mode(word)))")?
+                }
+                _ => {}
             }
-            print!("\n{}}}", pfx(lvl));
         }
+        Ok(true)
+    }
+
+    fn fits_32_bits(t: &BtfEnum) -> bool {
+        let vals = || t.values.iter().map(|v| t.value_of(v));
+        vals().all(|v| i32::try_from(v).is_ok()) || vals().all(|v| u32::try_from(v).is_ok())
     }
 
-    fn emit_fwd_def(&mut self, id: u32, t: &'a BtfFwd) {
-        if NAMES_BLACKLIST.is_match(&t.name) {
-            return;
+    fn emit_fwd_def(&mut self, id: u32, t: &'a BtfFwd) -> io::Result<bool> {
+        if self.names_blacklist.is_match(t.name) {
+            self.record_skipped(id, SkipReason::Blacklisted);
+            return Ok(false);
         }
         let name = self.resolve_type_name(NamedKind::Type, id, t.name);
         match t.kind {
-            BtfFwdKind::Struct => print!("struct {}", name),
+            BtfFwdKind::Struct => write!(self.writer, "struct {}", name)?,
             BtfFwdKind::Union => {
                 if self.cfg.union_as_struct {
-                    print!("struct /*union*/ {}", name)
+                    write!(self.writer, "struct /*union*/ {}", name)?
                 } else {
-                    print!("union {}", name)
+                    write!(self.writer, "union {}", name)?
                 }
             }
         }
+        Ok(true)
     }
 
-    fn emit_typedef_def(&mut self, id: u32, t: &'a BtfTypedef, lvl: usize) -> bool {
-        if NAMES_BLACKLIST.is_match(&t.name) {
-            return false;
+    fn emit_typedef_def(&mut self, id: u32, t: &'a BtfTypedef, lvl: usize) -> io::Result<bool> {
+        if self.names_blacklist.is_match(t.name) {
+            self.record_skipped(id, SkipReason::Blacklisted);
+            return Ok(false);
         }
         let name = self.resolve_type_name(NamedKind::Ident, id, t.name);
-        print!("typedef ");
-        self.emit_type_decl(t.type_id, &name, lvl);
-        return true;
+        write!(self.writer, "typedef ")?;
+        self.emit_type_decl(t.type_id, &name, lvl)?;
+        Ok(true)
     }
 
-    fn emit_type_decl(&mut self, mut id: u32, fname: &str, lvl: usize) {
+    /// Returns C declaration of a variable or field `field_name` of given type (e.g., `const
+    /// char *argv[]`), or of an abstract one (e.g., `int (*)(void *)`), if name is empty. Types
+    /// are referred to by the same names as in dumped definitions, anonymous ones are defined
+    /// inline. Nothing is written into dumper's writer.
+    pub fn type_decl_string(&mut self, type_id: u32, field_name: &str) -> BtfResult<String> {
+        match self.btf.type_by_id_opt(type_id) {
+            None => return btf_error(format!("Type ID {} is out of bounds", type_id)),
+            Some(BtfType::Func(_))
+            | Some(BtfType::Var(_))
+            | Some(BtfType::Datasec(_))
+            | Some(BtfType::DeclTag(_)) => {
+                return btf_error(format!(
+                    "[{}] {} has no C type declaration",
+                    type_id,
+                    self.btf.full_name(type_id)
+                ));
+            }
+            Some(_) => {}
+        }
+        let prev = self.writer.capture.replace(Vec::new());
+        let res = self.emit_type_decl(type_id, field_name, 0);
+        let buf = std::mem::replace(&mut self.writer.capture, prev).unwrap_or_default();
+        res?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    fn emit_type_decl(&mut self, mut id: u32, fname: &str, lvl: usize) -> io::Result<()> {
         // This algorithm emits correct C syntax for any type definition.
         //
         // For most types it's trivial, but there are few quirky type declaration  cases worth
@@ -542,24 +1435,25 @@ impl<'a> CDumper<'a> {
                 BtfType::Const(t) => id = t.type_id,
                 BtfType::Volatile(t) => id = t.type_id,
                 BtfType::Restrict(t) => id = t.type_id,
+                BtfType::TypeTag(t) => id = t.type_id,
                 BtfType::Array(t) => id = t.val_type_id,
                 BtfType::FuncProto(t) => id = t.res_type_id,
                 BtfType::Var(_) | BtfType::Datasec(_) | BtfType::Func(_) => {
                     chain.pop();
-                    print!("!@#! UNEXPECT TYPE DECL CHAIN ");
+                    write!(self.writer, "!@#! UNEXPECT TYPE DECL CHAIN ")?;
                     for parent_id in chain.iter().rev() {
-                        print!("[{}] --> ", parent_id);
+                        write!(self.writer, "[{}] --> ", parent_id)?;
                     }
-                    print!("[{}] {}", id, self.btf.type_by_id(id));
-                    return;
+                    write!(self.writer, "[{}] {}", id, self.btf.type_by_id(id))?;
+                    return Ok(());
                 }
                 _ => break,
             }
         }
-        self.emit_type_chain(chain, fname, lvl);
+        self.emit_type_chain(chain, fname, lvl)
     }
 
-    fn emit_type_chain(&mut self, mut chain: Vec<u32>, fname: &str, lvl: usize) {
+    fn emit_type_chain(&mut self, mut chain: Vec<u32>, fname: &str, lvl: usize) -> io::Result<()> {
         // default to true, in case we have single ptr in a chain. E.g., in ptr -> func_proto case.
         // func_proto will start a new emit_type_chain with just ptr, which should be emitted as
         // (*) or (*<fname>), so we don't want to preprend space for that last ptr.
@@ -567,54 +1461,60 @@ impl<'a> CDumper<'a> {
         while let Some(id) = chain.pop() {
             match self.btf.type_by_id(id) {
                 BtfType::Void => {
-                    self.emit_mods(&mut chain);
-                    print!("void");
+                    self.emit_mods(&mut chain)?;
+                    write!(self.writer, "void")?;
                 }
                 BtfType::Int(t) => {
-                    self.emit_mods(&mut chain);
-                    print!("{}", t.name);
+                    self.emit_mods(&mut chain)?;
+                    write!(self.writer, "{}", int_name(&self.cfg, t))?;
                 }
                 BtfType::Struct(t) | BtfType::Union(t) => {
-                    self.emit_mods(&mut chain);
-                    if t.name.is_empty() {
-                        self.emit_composite_def(id, t, lvl); // inline anonymous struct
+                    self.emit_mods(&mut chain)?;
+                    if self.is_anon_typedef(id) {
+                        let name = self.anon_typedef_name(id);
+                        write!(self.writer, "{}", name)?;
+                    } else if t.name.is_empty() {
+                        self.emit_composite_def(id, t, lvl)?; // inline anonymous struct
+                        let kind = if t.is_struct { "struct" } else { "union" };
+                        self.emit_anon_type_id(kind, id)?;
                     } else {
-                        self.emit_composite_fwd(id, t);
+                        self.emit_composite_fwd(id, t)?;
                     }
                 }
-                BtfType::Enum(t) => {
-                    self.emit_mods(&mut chain);
+                BtfType::Enum(t) | BtfType::Enum64(t) => {
+                    self.emit_mods(&mut chain)?;
                     if t.name.is_empty() {
-                        self.emit_enum_def(id, t, lvl); // inline anonymous enum
+                        self.emit_enum_def(id, t, lvl)?; // inline anonymous enum
+                        self.emit_anon_type_id("enum", id)?;
                     } else {
                         let uniq_name = self.resolve_type_name(NamedKind::Type, id, t.name);
-                        print!("enum {}", &uniq_name);
+                        write!(self.writer, "enum {}", &uniq_name)?;
                     }
                 }
                 BtfType::Fwd(t) => {
-                    self.emit_mods(&mut chain);
-                    self.emit_fwd_def(id, t);
+                    self.emit_mods(&mut chain)?;
+                    self.emit_fwd_def(id, t)?;
                 }
                 BtfType::Typedef(t) => {
-                    self.emit_mods(&mut chain);
+                    self.emit_mods(&mut chain)?;
                     let uniq_name = self.resolve_type_name(NamedKind::Ident, id, t.name);
-                    print!("{}", &uniq_name);
+                    write!(self.writer, "{}", &uniq_name)?;
                 }
                 BtfType::Ptr(_) => {
                     if last_was_ptr {
-                        print!("*")
+                        write!(self.writer, "*")?
                     } else {
-                        print!(" *")
+                        write!(self.writer, " *")?
                     }
                 }
                 BtfType::Volatile(_) => {
-                    print!(" volatile");
+                    write!(self.writer, " volatile")?;
                 }
                 BtfType::Const(_) => {
-                    print!(" const");
+                    write!(self.writer, " const")?;
                 }
                 BtfType::Restrict(_) => {
-                    print!(" restrict");
+                    write!(self.writer, " restrict")?;
                 }
                 BtfType::Array(t) => {
                     // GCC has a bug (https://gcc.gnu.org/bugzilla/show_bug.cgi?id=8354) which
@@ -634,69 +1534,99 @@ impl<'a> CDumper<'a> {
                     if let Some(&next_id) = chain.last() {
                         let t = self.btf.type_by_id(next_id);
                         if !fname.is_empty() && !last_was_ptr {
-                            print!(" ");
+                            write!(self.writer, " ")?;
                         }
                         if t.kind() != BtfKind::Array {
-                            print!("(");
+                            write!(self.writer, "(")?;
                         }
-                        self.emit_type_chain(chain, fname, lvl);
+                        self.emit_type_chain(chain, fname, lvl)?;
                         if t.kind() != BtfKind::Array {
-                            print!(")");
+                            write!(self.writer, ")")?;
                         }
                     } else {
-                        self.emit_name(fname, last_was_ptr);
+                        self.emit_name(fname, last_was_ptr)?;
                     }
-                    print!("[{}]", t.nelems);
-                    return;
+                    write!(self.writer, "[{}]", t.nelems)?;
+                    return Ok(());
                 }
                 BtfType::FuncProto(t) => {
-                    self.emit_mods(&mut chain);
+                    // qualifiers of return type (e.g., const int (*f)()) were already emitted
+                    // along with return type, as they precede func_proto in the chain; what's
+                    // left are modifiers of func_proto itself
+                    self.emit_mods(&mut chain)?;
                     if chain.is_empty() {
-                        self.emit_name(fname, last_was_ptr);
+                        self.emit_name(fname, last_was_ptr)?;
                     } else {
-                        print!(" (");
-                        self.emit_type_chain(chain, fname, lvl);
-                        print!(")");
+                        // for func_proto returning pointer (e.g., int *(*f)()), return type's
+                        // pointer is immediately followed by declarator's parenthesis
+                        if last_was_ptr {
+                            write!(self.writer, "(")?;
+                        } else {
+                            write!(self.writer, " (")?;
+                        }
+                        self.emit_type_chain(chain, fname, lvl)?;
+                        write!(self.writer, ")")?;
                     }
-                    print!("(");
+                    write!(self.writer, "(")?;
                     //
                     // Clang for BPF target generates func_proto with no args as a func_proto with
                     // a single void arg (i.e., <ret-type> (*f)(void) vs just <ret_type> (*f)()).
                     // We are going to pretend there are no args for such case.
-                    let arg_cnt = t.params.len();
-                    if arg_cnt == 1 && t.params[0].type_id == 0 {
-                        print!(")");
-                        return;
+                    //
+                    // func_proto with genuinely zero params (e.g., produced by GCC) means the same
+                    // thing, as BTF doesn't distinguish C's unspecified args `()` from explicit
+                    // no args `(void)`. Both cases are emitted as `()`, which is accepted for
+                    // either declaration, while `(void)` would reject callers passing args.
+                    if t.is_void_args() {
+                        write!(self.writer, ")")?;
+                        return Ok(());
                     }
 
-                    for (i, p) in t.params.iter().enumerate() {
+                    for (i, p) in t.args().iter().enumerate() {
                         if i > 0 {
-                            print!(", ");
-                        }
-                        // func_proto with vararg has last arg of type 'void'
-                        if i == arg_cnt - 1 && t.params[arg_cnt - 1].type_id == 0 {
-                            print!("...");
-                        } else {
-                            self.emit_type_decl(p.type_id, &p.name, lvl);
+                            write!(self.writer, ", ")?;
                         }
+                        // Array params decay to pointers before compiler generates BTF, so C99
+                        // array param forms are encoded as (and emitted as) equivalent pointer
+                        // params:
+                        //   int a[4], int a[static 4] -> ptr -> int        -> int *a
+                        //   int a[const 4]            -> const -> ptr -> int -> int * const a
+                        //   int a[restrict 4]         -> restrict -> ptr -> int
+                        // `static` size hint is lost. Genuine array params (not produced by
+                        // compilers) are emitted as such, but, as for any array, modifiers of
+                        // array itself are dropped: const -> array -> int is emitted as
+                        // `int a[4]`.
+                        self.emit_type_decl(p.type_id, p.name, lvl)?;
+                    }
+                    if t.is_variadic() {
+                        write!(self.writer, ", ...")?;
                     }
-                    print!(")");
-                    return;
+                    write!(self.writer, ")")?;
+                    return Ok(());
                 }
                 BtfType::Float(t) => {
-                    self.emit_mods(&mut chain);
-                    print!("{}", t.name);
+                    self.emit_mods(&mut chain)?;
+                    write!(self.writer, "{}", t.name)?;
                 }
                 BtfType::TypeTag(t) => {
-                    self.emit_mods(&mut chain);
-                    print!(" __attribute__((btf_tag((\"{}\")))", &t.name);
+                    // type tags are transparent for layout purposes, but are preserved in
+                    // declaration right after the type they apply to, e.g.:
+                    // int __attribute__((btf_type_tag("user"))) *p;
+                    // modifiers following a tag are emitted after it, like for pointers:
+                    // char __attribute__((btf_type_tag("rcu"))) const *p;
+                    write!(
+                        self.writer,
+                        " __attribute__((btf_type_tag(\"{}\")))",
+                        &t.name
+                    )?;
                 }
                 BtfType::Func(_) | BtfType::Var(_) | BtfType::Datasec(_) | BtfType::DeclTag(_) => {
-                    print!(
+                    write!(
+                        self.writer,
                         "!@#! UNEXPECT TYPE DECL id: {}, type: {}",
                         id,
                         self.btf.type_by_id(id)
-                    );
+                    )?;
                 }
             }
             if let BtfType::Ptr(_) = self.btf.type_by_id(id) {
@@ -705,28 +1635,92 @@ impl<'a> CDumper<'a> {
                 last_was_ptr = false;
             }
         }
-        self.emit_name(fname, last_was_ptr);
+        self.emit_name(fname, last_was_ptr)
+    }
+
+    fn unsupported_int_reason(&self, t: &BtfInt) -> Option<&'static str> {
+        if t.bits > 64 && !self.cfg.target.has_int128 {
+            Some("128-bit integers are not supported by target")
+        } else if t.encoding == BtfIntEncoding::Bool && !self.cfg.target.has_bool {
+            Some("_Bool is not supported by target")
+        } else if self.cfg.strict && is_odd_width_int(t) {
+            // outside of bitfields such ints are referenced as wider C types of the same size
+            Some("integers of non-standard bit width or offset can only be bitfields")
+        } else {
+            None
+        }
+    }
+
+    /// Marks referenced anonymous structs/unions as emitted through typedefs, unless some of
+    /// the references require them to be defined inline: anonymous members (whose fields are
+    /// accessed as fields of enclosing type) and typedefs (which already name them).
+    fn mark_anon_typedefs(&mut self) {
+        let btf = self.btf;
+        let mut inline = vec![false; btf.type_cnt() as usize];
+        for (_, t) in btf.iter() {
+            match t {
+                BtfType::Struct(t) | BtfType::Union(t) => {
+                    for m in t.members.iter().filter(|m| m.name.is_empty()) {
+                        inline[btf.skip_mods(m.type_id) as usize] = true;
+                    }
+                }
+                BtfType::Typedef(t) => inline[btf.skip_mods(t.type_id) as usize] = true,
+                _ => {}
+            }
+        }
+        for (id, t) in btf.iter() {
+            if let BtfType::Struct(t) | BtfType::Union(t) = t {
+                let s = &mut self.state[id as usize];
+                s.anon_typedef = t.name.is_empty() && s.referenced && !inline[id as usize];
+            }
+        }
+    }
+
+    fn is_anon_typedef(&self, id: u32) -> bool {
+        self.state[id as usize].anon_typedef
+    }
+
+    fn anon_typedef_name(&mut self, id: u32) -> String {
+        self.resolve_type_name(NamedKind::Ident, id, format!("__anon_{}", id))
+    }
+
+    /// Anonymous enum not used by any type is there only to provide its constants, so it's
+    /// defined on its own
+    fn is_standalone_anon_enum(&self, id: u32) -> bool {
+        match self.btf.type_by_id(id) {
+            BtfType::Enum(t) | BtfType::Enum64(t) => {
+                t.name.is_empty() && !self.state[id as usize].referenced
+            }
+            _ => false,
+        }
+    }
+
+    fn odd_width_int(&self, id: u32) -> Option<&'a BtfInt<'a>> {
+        match self.btf.type_by_id(id) {
+            BtfType::Int(t) if is_odd_width_int(t) => Some(t),
+            _ => None,
+        }
     }
 
-    fn emit_name(&self, fname: &str, last_was_ptr: bool) {
+    fn emit_name(&mut self, fname: &str, last_was_ptr: bool) -> io::Result<()> {
         if last_was_ptr {
-            print!("{}", fname);
+            write!(self.writer, "{}", fname)
         } else {
-            print!("{}{}", sep(fname), fname);
+            write!(self.writer, "{}{}", sep(fname), fname)
         }
     }
 
-    fn emit_mods(&self, chain: &mut Vec<u32>) {
+    fn emit_mods(&mut self, chain: &mut Vec<u32>) -> io::Result<()> {
         while let Some(id) = chain.pop() {
             match self.btf.type_by_id(id) {
                 BtfType::Volatile(_) => {
-                    print!("volatile ");
+                    write!(self.writer, "volatile ")?;
                 }
                 BtfType::Const(_) => {
-                    print!("const ");
+                    write!(self.writer, "const ")?;
                 }
                 BtfType::Restrict(_) => {
-                    print!("restrict ");
+                    write!(self.writer, "restrict ")?;
                 }
                 _ => {
                     chain.push(id);
@@ -734,27 +1728,64 @@ impl<'a> CDumper<'a> {
                 }
             }
         }
+        Ok(())
+    }
+
+    fn resolve_all_names(&mut self) {
+        for id in 1..self.btf.type_cnt() {
+            match self.btf.type_by_id(id) {
+                BtfType::Struct(_) | BtfType::Union(_) if self.is_anon_typedef(id) => {
+                    self.anon_typedef_name(id);
+                }
+                BtfType::Struct(t) | BtfType::Union(t) => {
+                    self.resolve_type_name(NamedKind::Type, id, t.name);
+                }
+                BtfType::Fwd(t) => {
+                    self.resolve_type_name(NamedKind::Type, id, t.name);
+                }
+                BtfType::Enum(t) | BtfType::Enum64(t) => {
+                    self.resolve_type_name(NamedKind::Type, id, t.name);
+                    let val_names = t
+                        .values
+                        .iter()
+                        .map(|v| self.resolve_name(NamedKind::Ident, v.name))
+                        .collect();
+                    self.state[id as usize].val_names = val_names;
+                }
+                BtfType::Typedef(t) => {
+                    self.resolve_type_name(NamedKind::Ident, id, t.name);
+                }
+                _ => {}
+            }
+        }
     }
 
-    fn resolve_type_name(&mut self, kind: NamedKind, id: u32, name: &'a str) -> String {
+    fn resolve_type_name(
+        &mut self,
+        kind: NamedKind,
+        id: u32,
+        name: impl Into<Cow<'a, str>>,
+    ) -> String {
+        let name = name.into();
         if name.is_empty() {
             return EMPTY.to_owned();
         }
         let s = &mut self.state[id as usize];
         if s.name.is_empty() {
-            let version = self.names.entry((kind, name)).or_insert(0);
+            let version = self.names.entry((kind, name.clone())).or_insert(0);
             *version += 1;
             if *version == 1 {
                 s.name = name.to_string()
             } else {
                 s.name = format!("{}___{}", name, version)
             }
+            self.name_map.insert(id, s.name.clone());
         }
         s.name.clone()
     }
 
     fn resolve_name(&mut self, kind: NamedKind, name: &'a str) -> String {
-        let version = self.names.entry((kind, name)).or_insert(0);
+        let version = self.names.entry((kind, Cow::Borrowed(name))).or_insert(0);
         *version += 1;
         if *version == 1 {
             name.to_string()
@@ -764,14 +1795,94 @@ impl<'a> CDumper<'a> {
     }
 }
 
-lazy_static! {
-    static ref NAMES_BLACKLIST: RegexSet =
-        RegexSet::new(&["__builtin_va_list"]).expect("invalid blacklist regexes");
+/// Default names blacklist, see `CDumper::set_name_blacklist()`
+pub const BLACKLISTED_NAMES: &[&str] = &["__builtin_va_list"];
+
+#[cfg(not(feature = "no-regex"))]
+type NamesSet = RegexSet;
+
+/// Exact-match replacement for `RegexSet`
+#[cfg(feature = "no-regex")]
+struct NamesSet(HashSet<String>);
+
+#[cfg(feature = "no-regex")]
+impl NamesSet {
+    fn new(names: &[&str]) -> Result<NamesSet, std::convert::Infallible> {
+        Ok(NamesSet(names.iter().map(|&s| s.to_owned()).collect()))
+    }
+
+    fn is_match(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
 }
 
 const EMPTY: &str = "";
 const SPACE: &str = " ";
 const PREFIXES: &str = "\t\t\t\t\t\t\t\t\t\t\t\t";
+const SPACES: &str = "                                                ";
+
+/// Whether name is one of fixed-width int types defined by <stdint.h>
+fn is_stdint_name(name: &str) -> bool {
+    let name = name.strip_prefix('u').unwrap_or(name);
+    match name
+        .strip_prefix("int")
+        .and_then(|name| name.strip_suffix("_t"))
+    {
+        Some(width) => matches!(width, "8" | "16" | "32" | "64" | "ptr" | "max"),
+        None => false,
+    }
+}
+
+/// Name to emit int with, see `CDumperCfg::int_names`
+fn int_name<'s>(cfg: &'s CDumperCfg, t: &'s BtfInt) -> &'s str {
+    if is_odd_width_int(t) {
+        return bitfield_base_name(t);
+    }
+    if t.name == "char" {
+        return t.name;
+    }
+    match cfg.int_names.get(&(t.bits, t.encoding)) {
+        Some(name) => name,
+        None => t.name,
+    }
+}
+
+/// C type to reference an int of non-standard width or offset by, e.g., as a base type of a
+/// bitfield standing in for it: int's own name, if it's a C type already (e.g., `unsigned int`
+/// with only some of its bits used), or C type of the same size and signedness otherwise.
+fn bitfield_base_name<'s>(t: &BtfInt<'s>) -> &'s str {
+    let is_c_type = t.name.split(' ').all(|w| {
+        matches!(
+            w,
+            "signed" | "unsigned" | "char" | "short" | "int" | "long" | "_Bool" | "__int128"
+        )
+    });
+    if is_c_type {
+        return t.name;
+    }
+    match (t.sz, t.encoding) {
+        (1, BtfIntEncoding::Char) => "char",
+        (1, BtfIntEncoding::Signed) => "signed char",
+        (1, _) => "unsigned char",
+        (2, BtfIntEncoding::Signed) => "short int",
+        (2, _) => "short unsigned int",
+        (8, BtfIntEncoding::Signed) => "long long int",
+        (8, _) => "long long unsigned int",
+        (16, BtfIntEncoding::Signed) => "__int128",
+        (16, _) => "__int128 unsigned",
+        (_, BtfIntEncoding::Signed) => "int",
+        (_, _) => "unsigned int",
+    }
+}
+
+fn is_std_int_width(bits: u32) -> bool {
+    matches!(bits, 8 | 16 | 32 | 64 | 128)
+}
+
+/// Whether int can't be referenced by its own name, as it doesn't span all of its bytes
+fn is_odd_width_int(t: &BtfInt) -> bool {
+    !is_std_int_width(t.bits) || !t.is_full_width()
+}
 
 fn sep(name: &str) -> &str {
     if name.is_empty() {
@@ -781,10 +1892,13 @@ fn sep(name: &str) -> &str {
     }
 }
 
-fn pfx(lvl: usize) -> &'static str {
-    if lvl >= PREFIXES.len() {
-        PREFIXES
-    } else {
-        &PREFIXES[0..lvl]
+/// Indentation for a given nesting level, borrowed from static strings for common shallow
+/// levels, so that only pathologically nested types need allocation
+fn pfx(indent: Indent, lvl: usize) -> Cow<'static, str> {
+    match indent {
+        Indent::Tabs if lvl <= PREFIXES.len() => Cow::Borrowed(&PREFIXES[0..lvl]),
+        Indent::Tabs => Cow::Owned("\t".repeat(lvl)),
+        Indent::Spaces(n) if n * lvl <= SPACES.len() => Cow::Borrowed(&SPACES[0..n * lvl]),
+        Indent::Spaces(n) => Cow::Owned(" ".repeat(n * lvl)),
     }
 }
@@ -0,0 +1,184 @@
+use std::fmt::Write;
+
+use crate::types::*;
+
+/// Whether type is listed on its own in canonical text. All other types are spelled out in
+/// place, wherever they are referenced.
+fn is_canonical_root(t: &BtfType) -> bool {
+    match t {
+        BtfType::Void | BtfType::TypeTag(_) => false,
+        BtfType::DeclTag(_) => true,
+        _ => !t.name().is_empty(),
+    }
+}
+
+impl<'a> Btf<'a> {
+    /// Produces textual representation of all types that doesn't depend on their IDs or order:
+    /// each named type is printed on its own line, with types it references spelled out by
+    /// name (or in full, for anonymous ones), and lines sorted by name, then kind. Two BTFs
+    /// describing the same types produce identical text, which is handy for snapshot testing
+    /// and diffing.
+    pub fn to_canonical_text(&self) -> String {
+        let mut lines: Vec<(&str, &str, String)> = self
+            .iter()
+            .map(|(_, t)| t)
+            .filter(|t| is_canonical_root(t))
+            .map(|t| (t.name(), t.kind().keyword(), self.canonical_def(t)))
+            .collect();
+        lines.sort();
+        let mut out = String::new();
+        for (_, _, line) in lines {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out
+    }
+
+    fn canonical_def(&self, t: &BtfType) -> String {
+        let mut s = String::new();
+        match t {
+            BtfType::Int(t) => {
+                write!(s, "int {} bits:{} off:{}", t.name, t.bits, t.offset).unwrap();
+                if t.encoding != BtfIntEncoding::None {
+                    write!(s, " enc:{}", t.encoding).unwrap();
+                }
+            }
+            BtfType::Struct(_) | BtfType::Union(_) | BtfType::Enum(_) | BtfType::Enum64(_) => {
+                s = self.canonical_body(t);
+            }
+            BtfType::Fwd(t) => write!(s, "fwd {} {}", t.kind, t.name).unwrap(),
+            BtfType::Typedef(t) => {
+                write!(s, "typedef {} = {}", t.name, self.canonical_ref(t.type_id)).unwrap()
+            }
+            BtfType::Func(t) => write!(
+                s,
+                "func {} {}: {}",
+                t.name,
+                t.kind,
+                self.canonical_ref(t.proto_type_id)
+            )
+            .unwrap(),
+            BtfType::Var(t) => write!(
+                s,
+                "var {} {}: {}",
+                t.name,
+                t.kind,
+                self.canonical_ref(t.type_id)
+            )
+            .unwrap(),
+            BtfType::Datasec(t) => {
+                write!(s, "datasec {} sz:{} {{", t.name, t.sz).unwrap();
+                for v in &t.vars {
+                    write!(
+                        s,
+                        " {} off:{} sz:{};",
+                        self.canonical_ref(v.type_id),
+                        v.offset,
+                        v.sz
+                    )
+                    .unwrap();
+                }
+                s.push_str(" }");
+            }
+            BtfType::Float(t) => write!(s, "float {} sz:{}", t.name, t.sz).unwrap(),
+            BtfType::DeclTag(t) => write!(
+                s,
+                "decl_tag {} comp_idx:{} -> {}",
+                t.name,
+                t.comp_idx,
+                self.canonical_ref(t.type_id)
+            )
+            .unwrap(),
+            _ => s = self.canonical_ref_type(t),
+        }
+        s
+    }
+
+    /// Struct, union, or enum along with all its members
+    fn canonical_body(&self, t: &BtfType) -> String {
+        let mut s = String::new();
+        match t {
+            BtfType::Struct(t) | BtfType::Union(t) => {
+                let kw = if t.is_struct { "struct" } else { "union" };
+                write!(s, "{}{}{} sz:{} {{", kw, sep(t.name), t.name, t.sz).unwrap();
+                for m in &t.members {
+                    write!(
+                        s,
+                        " {}: {} off:{}",
+                        m.name,
+                        self.canonical_ref(m.type_id),
+                        m.bit_offset
+                    )
+                    .unwrap();
+                    if m.bit_size != 0 {
+                        write!(s, " bits:{}", m.bit_size).unwrap();
+                    }
+                    s.push(';');
+                }
+                s.push_str(" }");
+            }
+            BtfType::Enum(t) | BtfType::Enum64(t) => {
+                let kw = if t.is_64 { "enum64" } else { "enum" };
+                write!(s, "{}{}{} sz:{} {{", kw, sep(t.name), t.name, t.sz).unwrap();
+                for v in &t.values {
+                    write!(s, " {} = {};", v.name, t.value_of(v)).unwrap();
+                }
+                s.push_str(" }");
+            }
+            _ => unreachable!(),
+        }
+        s
+    }
+
+    fn canonical_ref(&self, type_id: u32) -> String {
+        self.canonical_ref_type(self.type_by_id(type_id))
+    }
+
+    /// Spells out type as referenced by other types: by kind and name for named types, or
+    /// in full for anonymous ones. Anonymous types can't form cycles without going through a
+    /// named type, so this always terminates.
+    fn canonical_ref_type(&self, t: &BtfType) -> String {
+        if is_canonical_root(t) {
+            return format!("{} {}", t.kind().keyword(), t.name());
+        }
+        match t {
+            BtfType::Void => "void".to_owned(),
+            BtfType::Ptr(t) => format!("ptr -> {}", self.canonical_ref(t.type_id)),
+            BtfType::Array(t) => format!(
+                "array[{}] of {}",
+                t.nelems,
+                self.canonical_ref(t.val_type_id)
+            ),
+            BtfType::Volatile(t) => format!("volatile {}", self.canonical_ref(t.type_id)),
+            BtfType::Const(t) => format!("const {}", self.canonical_ref(t.type_id)),
+            BtfType::Restrict(t) => format!("restrict {}", self.canonical_ref(t.type_id)),
+            BtfType::TypeTag(t) => {
+                format!("type_tag({}) {}", t.name, self.canonical_ref(t.type_id))
+            }
+            BtfType::FuncProto(t) => {
+                let params: Vec<String> = t
+                    .params
+                    .iter()
+                    .map(|p| format!("{}: {}", p.name, self.canonical_ref(p.type_id)))
+                    .collect();
+                format!(
+                    "func_proto ({}) -> {}",
+                    params.join(", "),
+                    self.canonical_ref(t.res_type_id)
+                )
+            }
+            BtfType::Struct(_) | BtfType::Union(_) | BtfType::Enum(_) | BtfType::Enum64(_) => {
+                self.canonical_body(t)
+            }
+            _ => format!("{} <anon>", t.kind().keyword()),
+        }
+    }
+}
+
+fn sep(name: &str) -> &str {
+    if name.is_empty() {
+        ""
+    } else {
+        " "
+    }
+}
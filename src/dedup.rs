@@ -0,0 +1,352 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::types::*;
+use crate::{btf_error, BtfResult};
+
+/// Number of rounds of mixing in hashes of referenced types into type's own hash. Types
+/// with equal hashes are only candidates for equality, so this just needs to be deep enough
+/// to keep candidate buckets small.
+const HASH_DEPTH: usize = 3;
+
+/// Type with all referenced type IDs zeroed out, i.e., only the parts of it that can be
+/// compared directly
+fn shape<'a>(t: &BtfType<'a>) -> BtfType<'a> {
+    let mut t = t.clone();
+    t.remap_type_ids(|_| 0);
+    t
+}
+
+fn ref_ids(t: &BtfType) -> Vec<u32> {
+    let mut ids = Vec::new();
+    t.visit_type_ids(|id| ids.push(id));
+    ids
+}
+
+/// Whether one of the types is a forward declaration and the other one is a struct/union
+/// definition it can refer to
+fn fwd_matches_def(t1: &BtfType, t2: &BtfType) -> bool {
+    let matches = |fwd: &BtfType, def: &BtfType| match (fwd, def) {
+        (BtfType::Fwd(fwd), BtfType::Struct(def)) => {
+            fwd.kind == BtfFwdKind::Struct && fwd.name == def.name
+        }
+        (BtfType::Fwd(fwd), BtfType::Union(def)) => {
+            fwd.kind == BtfFwdKind::Union && fwd.name == def.name
+        }
+        _ => false,
+    };
+    matches(t1, t2) || matches(t2, t1)
+}
+
+/// Classes of types already proven to be structurally equal (union-find over type IDs)
+struct EquivClasses {
+    parent: Vec<u32>,
+}
+
+impl EquivClasses {
+    fn new(cnt: u32) -> EquivClasses {
+        EquivClasses {
+            parent: (0..cnt).collect(),
+        }
+    }
+
+    fn find(&mut self, mut id: u32) -> u32 {
+        while self.parent[id as usize] != id {
+            let grand_parent = self.parent[self.parent[id as usize] as usize];
+            self.parent[id as usize] = grand_parent;
+            id = grand_parent;
+        }
+        id
+    }
+
+    fn union(&mut self, id1: u32, id2: u32) {
+        let (r1, r2) = (self.find(id1), self.find(id2));
+        self.parent[r1.max(r2) as usize] = r1.min(r2);
+    }
+}
+
+impl<'a> Btf<'a> {
+    /// Checks whether two types, possibly from different BTFs, are structurally equal, following
+    /// references in each respective BTF, e.g., to find structs that changed between kernel
+    /// versions. Forward declarations are considered equal to struct/union definitions of the
+    /// same name and kind. Recursive types are handled by assuming pairs of types being compared
+    /// to be equal until proven otherwise.
+    pub fn types_equal(a: (&Btf, u32), b: (&Btf, u32)) -> bool {
+        Btf::types_equal_memo(a, b, &mut HashSet::new())
+    }
+
+    /// Same as `types_equal()`, but skips pairs of types in `equal`, adding all the pairs
+    /// compared along the way there, if types turn out to be equal. This saves repeated
+    /// comparisons of the same types when comparing lots of types between the same two BTFs.
+    pub(crate) fn types_equal_memo(
+        a: (&Btf, u32),
+        b: (&Btf, u32),
+        equal: &mut HashSet<(u32, u32)>,
+    ) -> bool {
+        let (btf1, btf2) = (a.0, b.0);
+        let mut visited = HashSet::new();
+        let mut pending = vec![(a.1, b.1)];
+        while let Some((id1, id2)) = pending.pop() {
+            if equal.contains(&(id1, id2)) || !visited.insert((id1, id2)) {
+                continue;
+            }
+            let t1 = btf1.type_by_id(id1);
+            let t2 = btf2.type_by_id(id2);
+            if fwd_matches_def(t1, t2) {
+                continue;
+            }
+            if shape(t1) != shape(t2) {
+                return false;
+            }
+            pending.extend(ref_ids(t1).into_iter().zip(ref_ids(t2)));
+        }
+        equal.extend(visited);
+        true
+    }
+
+    /// Deduplicates types, similarly to libbpf's `btf__dedup()`: each group of structurally
+    /// equal types (see `find_duplicates()`) is collapsed into its first type, and forward
+    /// declarations are replaced with the struct/union definition of the same name, as long as
+    /// there is only one such definition. Resolving forward declarations can make more types
+    /// equal (e.g., struct copies referencing forward-declared and fully defined struct), so
+    /// this is repeated until nothing changes. Surviving types keep their relative order and
+    /// references to them are renumbered accordingly. .BTF.ext data is not carried over.
+    pub fn dedup(&self) -> BtfResult<Btf<'a>> {
+        if self.base_type_cnt() != 0 {
+            return btf_error("Deduplicating split BTF is not supported".to_owned());
+        }
+        let mut btf = self.with_types(self.types().to_vec());
+        loop {
+            let map = btf.dedup_map();
+            if map.iter().enumerate().all(|(id, &rep)| id as u32 == rep) {
+                return Ok(btf);
+            }
+            let mut id_map = vec![0; map.len()];
+            let mut types = Vec::new();
+            for (id, t) in btf.iter() {
+                if map[id as usize] == id {
+                    id_map[id as usize] = types.len() as u32;
+                    types.push(t.clone());
+                }
+            }
+            for t in &mut types {
+                t.remap_type_ids(|id| id_map[map[id as usize] as usize]);
+            }
+            btf = btf.with_types(types);
+        }
+    }
+
+    /// Maps each type ID to the ID of the type it should be replaced with, which is either the
+    /// first one of its duplicates, or struct/union definition, for forward declarations.
+    fn dedup_map(&self) -> Vec<u32> {
+        let mut map: Vec<u32> = (0..self.type_cnt()).collect();
+        for group in self.find_duplicates() {
+            for &id in &group[1..] {
+                map[id as usize] = group[0];
+            }
+        }
+        for (id, t) in self.iter_kind(BtfKind::Fwd) {
+            let fwd = match t {
+                BtfType::Fwd(fwd) => fwd,
+                _ => continue,
+            };
+            let defs: BTreeSet<u32> = self
+                .all_by_name(fwd.name)
+                .into_iter()
+                .filter(|&def_id| match self.type_by_id(def_id) {
+                    BtfType::Struct(_) => fwd.kind == BtfFwdKind::Struct,
+                    BtfType::Union(_) => fwd.kind == BtfFwdKind::Union,
+                    _ => false,
+                })
+                .map(|def_id| map[def_id as usize])
+                .collect();
+            if defs.len() == 1 {
+                map[id as usize] = *defs.iter().next().unwrap();
+            }
+        }
+        map
+    }
+
+    /// Finds groups of structurally equal types, i.e., types of the same kind and name, with
+    /// the same members, values, sizes, etc., that reference structurally equal types in the
+    /// same positions. Referenced types are compared by their structure, not by IDs, so
+    /// identical structs coming from different compilation units are recognized as duplicates,
+    /// even if they reference their own copies of other types. Each group lists type IDs in
+    /// ascending order; only groups of two or more types are returned, ordered by their first
+    /// type ID.
+    pub fn find_duplicates(&self) -> Vec<Vec<u32>> {
+        let hashes = self.struct_hashes();
+        let mut buckets: HashMap<u64, Vec<u32>> = HashMap::new();
+        for id in 1..self.type_cnt() {
+            buckets.entry(hashes[id as usize]).or_default().push(id);
+        }
+
+        let mut classes = EquivClasses::new(self.type_cnt());
+        let mut dups = Vec::new();
+        for (_, ids) in buckets {
+            if ids.len() < 2 {
+                continue;
+            }
+            let mut groups: Vec<Vec<u32>> = Vec::new();
+            for id in ids {
+                match groups
+                    .iter_mut()
+                    .find(|g| self.types_equiv(g[0], id, &mut classes))
+                {
+                    Some(g) => g.push(id),
+                    None => groups.push(vec![id]),
+                }
+            }
+            dups.extend(groups.into_iter().filter(|g| g.len() > 1));
+        }
+        dups.sort();
+        dups
+    }
+
+    /// Hashes of all types that are equal for structurally equal types
+    fn struct_hashes(&self) -> Vec<u64> {
+        let hash_of = |v: &dyn Fn(&mut DefaultHasher)| {
+            let mut h = DefaultHasher::new();
+            v(&mut h);
+            h.finish()
+        };
+        let shallow: Vec<u64> = self
+            .types()
+            .iter()
+            .map(|t| hash_of(&|h| shape(t).hash(h)))
+            .collect();
+        let mut hashes = shallow.clone();
+        for _ in 0..HASH_DEPTH {
+            hashes = self
+                .types()
+                .iter()
+                .enumerate()
+                .map(|(id, t)| {
+                    hash_of(&|h| {
+                        shallow[id].hash(h);
+                        t.visit_type_ids(|ref_id| hashes[ref_id as usize].hash(h));
+                    })
+                })
+                .collect();
+        }
+        hashes
+    }
+
+    /// Checks whether two types are structurally equal. Pairs of types being compared are
+    /// assumed to be equal until proven otherwise, which terminates comparison of recursive
+    /// types. Once types turn out to be equal, so are all the pairs compared along the way,
+    /// which is remembered in `classes` to not compare them ever again.
+    fn types_equiv(&self, id1: u32, id2: u32, classes: &mut EquivClasses) -> bool {
+        let mut assumed = HashSet::new();
+        let mut pending = vec![(id1, id2)];
+        while let Some((id1, id2)) = pending.pop() {
+            if classes.find(id1) == classes.find(id2) || !assumed.insert((id1, id2)) {
+                continue;
+            }
+            let t1 = self.type_by_id(id1);
+            let t2 = self.type_by_id(id2);
+            if shape(t1) != shape(t2) {
+                return false;
+            }
+            pending.extend(ref_ids(t1).into_iter().zip(ref_ids(t2)));
+        }
+        for (id1, id2) in assumed {
+            classes.union(id1, id2);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::BtfBuilder;
+
+    fn member(name: &str, type_id: u32, bit_offset: u32) -> BtfMember<'_> {
+        BtfMember {
+            name,
+            type_id,
+            bit_offset,
+            bit_size: 0,
+        }
+    }
+
+    /// Adds `struct a { int x; struct b *b; }` and `struct b { int y; struct a *a; }`, as
+    /// emitted for a single compilation unit, returning IDs of int, `struct b *` and both
+    /// structs.
+    fn add_cu(b: &mut BtfBuilder) -> (u32, u32, u32, u32) {
+        let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+        let fwd_b = b.add_fwd("b", BtfFwdKind::Struct).unwrap();
+        let ptr_b = b.add_ptr(fwd_b).unwrap();
+        let a = b
+            .add_struct("a", 16, vec![member("x", int, 0), member("b", ptr_b, 64)])
+            .unwrap();
+        let ptr_a = b.add_ptr(a).unwrap();
+        let s_b = b
+            .add_struct("b", 16, vec![member("y", int, 0), member("a", ptr_a, 64)])
+            .unwrap();
+        (int, ptr_b, a, s_b)
+    }
+
+    /// Two copies of mutually referencing structs plus `struct a` differing in a member name
+    fn two_cus() -> Btf<'static> {
+        let mut b = BtfBuilder::new();
+        add_cu(&mut b);
+        let (int, ptr_b, _, _) = add_cu(&mut b);
+        b.add_struct(
+            "a",
+            16,
+            vec![member("other", int, 0), member("b", ptr_b, 64)],
+        )
+        .unwrap();
+        b.build()
+    }
+
+    #[test]
+    fn find_duplicates_groups_equal_types() {
+        let btf = two_cus();
+        assert_eq!(btf.type_cnt(), 14);
+        assert_eq!(
+            btf.find_duplicates(),
+            vec![
+                vec![1, 7],
+                vec![2, 8],
+                vec![3, 9],
+                vec![4, 10],
+                vec![5, 11],
+                vec![6, 12]
+            ]
+        );
+        assert!(Btf::types_equal((&btf, 4), (&btf, 10)));
+        assert!(!Btf::types_equal((&btf, 4), (&btf, 13)));
+        // forward declaration is equal to the definition it refers to
+        assert!(Btf::types_equal((&btf, 2), (&btf, 6)));
+    }
+
+    #[test]
+    fn dedup_remaps_references() {
+        let btf = two_cus().dedup().unwrap();
+        let names: Vec<String> = (0..btf.type_cnt()).map(|id| btf.full_name(id)).collect();
+        assert_eq!(
+            names,
+            [
+                "void",
+                "int",
+                "<anon ptr>",
+                "struct a",
+                "<anon ptr>",
+                "struct b",
+                "struct a"
+            ]
+        );
+        let ref_ids = |id| ref_ids(btf.type_by_id(id));
+        // `struct b *` now points to definition instead of forward declaration
+        assert_eq!(ref_ids(2), [5]);
+        assert_eq!(ref_ids(3), [1, 2]);
+        assert_eq!(ref_ids(4), [3]);
+        assert_eq!(ref_ids(5), [1, 4]);
+        assert_eq!(ref_ids(6), [1, 2]);
+        assert!(btf.find_duplicates().is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::types::*;
+
+/// Placement of a struct/union field, as compared by `btf_diff()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FieldLayout {
+    pub bit_offset: u32,
+    /// number of bits field occupies, for non-bitfield fields it's the size of its type
+    pub bit_size: u32,
+}
+
+/// Field of a changed struct/union that got moved, resized, added (no `old` layout), or removed
+/// (no `new` layout). Fields of anonymous embedded structs/unions are reported as fields of the
+/// enclosing type, with offsets relative to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    pub name: String,
+    pub old: Option<FieldLayout>,
+    pub new: Option<FieldLayout>,
+}
+
+/// Difference between named types of two BTFs, see `btf_diff()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDiff {
+    /// type with given ID in new BTF has no counterpart in old BTF
+    Added {
+        id: u32,
+        kind: BtfKind,
+        name: String,
+    },
+    /// type with given ID in old BTF has no counterpart in new BTF
+    Removed {
+        id: u32,
+        kind: BtfKind,
+        name: String,
+    },
+    /// type is present in both BTFs, but is not structurally equal; `fields` lists layout
+    /// changes of structs/unions and is empty if only referenced types changed
+    Changed {
+        old_id: u32,
+        new_id: u32,
+        kind: BtfKind,
+        name: String,
+        old_size: Option<u32>,
+        new_size: Option<u32>,
+        fields: Vec<FieldDiff>,
+    },
+}
+
+fn fmt_size(sz: Option<u32>) -> String {
+    sz.map_or_else(|| "?".to_owned(), |sz| sz.to_string())
+}
+
+fn fmt_field(l: Option<FieldLayout>) -> String {
+    l.map_or_else(
+        || "-".to_owned(),
+        |l| format!("off:{} bits:{}", l.bit_offset, l.bit_size),
+    )
+}
+
+impl fmt::Display for TypeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeDiff::Added { id, kind, name } => write!(f, "+ {:?} '{}' [{}]", kind, name, id),
+            TypeDiff::Removed { id, kind, name } => write!(f, "- {:?} '{}' [{}]", kind, name, id),
+            TypeDiff::Changed {
+                old_id,
+                new_id,
+                kind,
+                name,
+                old_size,
+                new_size,
+                fields,
+            } => {
+                write!(f, "~ {:?} '{}' [{} -> {}]", kind, name, old_id, new_id)?;
+                if old_size != new_size {
+                    write!(f, " sz:{} -> {}", fmt_size(*old_size), fmt_size(*new_size))?;
+                }
+                for fd in fields {
+                    write!(
+                        f,
+                        "\n\t'{}' {} -> {}",
+                        fd.name,
+                        fmt_field(fd.old),
+                        fmt_field(fd.new)
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Named types of BTF, grouped by kind and name, with IDs in ascending order
+fn named_types<'b>(btf: &'b Btf) -> HashMap<(BtfKind, &'b str), Vec<u32>> {
+    let mut types: HashMap<(BtfKind, &str), Vec<u32>> = HashMap::new();
+    for (id, t) in btf.iter() {
+        if t.name().is_empty() || matches!(t, BtfType::DeclTag(_) | BtfType::TypeTag(_)) {
+            continue;
+        }
+        types.entry((t.kind(), t.name())).or_default().push(id);
+    }
+    types
+}
+
+/// Named fields of struct/union, including fields of anonymous embedded structs/unions
+fn field_layouts(btf: &Btf, id: u32, base_off: u32, fields: &mut Vec<(String, FieldLayout)>) {
+    let layout = match btf.struct_layout(id) {
+        Ok(layout) => layout,
+        Err(_) => return,
+    };
+    for m in &layout.members {
+        if !m.name.is_empty() {
+            let l = FieldLayout {
+                bit_offset: base_off + m.bit_offset,
+                bit_size: m.bit_size,
+            };
+            fields.push((m.name.to_owned(), l));
+            continue;
+        }
+        let inner_id = btf.skip_mods_and_typedefs(m.type_id);
+        if let BtfType::Struct(_) | BtfType::Union(_) = btf.type_by_id(inner_id) {
+            field_layouts(btf, inner_id, base_off + m.bit_offset, fields);
+        }
+    }
+}
+
+/// Fields of two versions of the same struct/union that differ in offset or size, in the
+/// order of new version, followed by removed fields
+fn field_diffs(old: &Btf, old_id: u32, new: &Btf, new_id: u32) -> Vec<FieldDiff> {
+    let (mut old_fields, mut new_fields) = (Vec::new(), Vec::new());
+    field_layouts(old, old_id, 0, &mut old_fields);
+    field_layouts(new, new_id, 0, &mut new_fields);
+    let mut diffs = Vec::new();
+    for (name, l) in &new_fields {
+        let old_l = old_fields.iter().find(|(n, _)| n == name).map(|(_, l)| *l);
+        if old_l != Some(*l) {
+            diffs.push(FieldDiff {
+                name: name.clone(),
+                old: old_l,
+                new: Some(*l),
+            });
+        }
+    }
+    for (name, l) in old_fields {
+        if !new_fields.iter().any(|(n, _)| *n == name) {
+            diffs.push(FieldDiff {
+                name,
+                old: Some(l),
+                new: None,
+            });
+        }
+    }
+    diffs
+}
+
+/// Compares named types of two BTFs, e.g., of two kernel versions. Types are matched by kind
+/// and name; types present in only one of BTFs are reported as removed or added, while types
+/// present in both are reported as changed if they are not structurally equal (see
+/// `Btf::types_equal()`), which includes changes of any types they reference. If there are
+/// multiple types of the same kind and name, they are considered unchanged as long as any
+/// pair of them is equal, otherwise the first ones are reported. Anonymous types and type/decl
+/// tags are not matched on their own. Removed types come first, in the order of old BTF,
+/// followed by added and changed types in the order of new BTF.
+pub fn btf_diff(old: &Btf, new: &Btf) -> Vec<TypeDiff> {
+    let old_types = named_types(old);
+    let new_types = named_types(new);
+    let mut equal = HashSet::new();
+    let mut diffs = Vec::new();
+    for (id, t) in old.iter() {
+        let key = (t.kind(), t.name());
+        if old_types.get(&key).is_some_and(|ids| ids[0] == id) && !new_types.contains_key(&key) {
+            diffs.push(TypeDiff::Removed {
+                id,
+                kind: key.0,
+                name: key.1.to_owned(),
+            });
+        }
+    }
+    for (new_id, t) in new.iter() {
+        let key = (t.kind(), t.name());
+        let new_ids = match new_types.get(&key) {
+            Some(ids) if ids[0] == new_id => ids,
+            _ => continue,
+        };
+        let old_ids = match old_types.get(&key) {
+            Some(ids) => ids,
+            None => {
+                diffs.push(TypeDiff::Added {
+                    id: new_id,
+                    kind: key.0,
+                    name: key.1.to_owned(),
+                });
+                continue;
+            }
+        };
+        let same = old_ids.iter().any(|&old_id| {
+            new_ids
+                .iter()
+                .any(|&id| Btf::types_equal_memo((old, old_id), (new, id), &mut equal))
+        });
+        if same {
+            continue;
+        }
+        let old_id = old_ids[0];
+        diffs.push(TypeDiff::Changed {
+            old_id,
+            new_id,
+            kind: key.0,
+            name: key.1.to_owned(),
+            old_size: old.get_size_of(old_id).ok(),
+            new_size: new.get_size_of(new_id).ok(),
+            fields: field_diffs(old, old_id, new, new_id),
+        });
+    }
+    diffs
+}
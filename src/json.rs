@@ -0,0 +1,271 @@
+use std::fmt::Write;
+
+use crate::types::*;
+
+/// Minimal JSON document model, just enough to describe BTF types
+enum Json<'a> {
+    Num(i128),
+    Str(&'a str),
+    Arr(Vec<Json<'a>>),
+    Obj(Vec<(&'static str, Json<'a>)>),
+}
+
+impl<'a> Json<'a> {
+    fn write(&self, out: &mut String, pretty: bool, lvl: usize) {
+        match self {
+            Json::Num(n) => write!(out, "{}", n).unwrap(),
+            Json::Str(s) => write_str(out, s),
+            Json::Arr(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, pretty, lvl + 1);
+                    item.write(out, pretty, lvl + 1);
+                }
+                if !items.is_empty() {
+                    newline(out, pretty, lvl);
+                }
+                out.push(']');
+            }
+            Json::Obj(fields) => {
+                out.push('{');
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, pretty, lvl + 1);
+                    write_str(out, k);
+                    out.push_str(if pretty { ": " } else { ":" });
+                    v.write(out, pretty, lvl + 1);
+                }
+                if !fields.is_empty() {
+                    newline(out, pretty, lvl);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn newline(out: &mut String, pretty: bool, lvl: usize) {
+    if pretty {
+        out.push('\n');
+        for _ in 0..lvl {
+            out.push_str("  ");
+        }
+    }
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn num<T: Into<i128>>(n: T) -> Json<'static> {
+    Json::Num(n.into())
+}
+
+/// Kind name, as used by bpftool
+pub(crate) fn kind_name(t: &BtfType) -> &'static str {
+    match t {
+        BtfType::Void => "VOID",
+        BtfType::Int(_) => "INT",
+        BtfType::Ptr(_) => "PTR",
+        BtfType::Array(_) => "ARRAY",
+        BtfType::Struct(_) => "STRUCT",
+        BtfType::Union(_) => "UNION",
+        BtfType::Enum(_) => "ENUM",
+        BtfType::Enum64(_) => "ENUM64",
+        BtfType::Fwd(_) => "FWD",
+        BtfType::Typedef(_) => "TYPEDEF",
+        BtfType::Volatile(_) => "VOLATILE",
+        BtfType::Const(_) => "CONST",
+        BtfType::Restrict(_) => "RESTRICT",
+        BtfType::Func(_) => "FUNC",
+        BtfType::FuncProto(_) => "FUNC_PROTO",
+        BtfType::Var(_) => "VAR",
+        BtfType::Datasec(_) => "DATASEC",
+        BtfType::Float(_) => "FLOAT",
+        BtfType::DeclTag(_) => "DECL_TAG",
+        BtfType::TypeTag(_) => "TYPE_TAG",
+    }
+}
+
+pub(crate) fn int_encoding_name(enc: BtfIntEncoding) -> &'static str {
+    match enc {
+        BtfIntEncoding::None => "(none)",
+        BtfIntEncoding::Signed => "SIGNED",
+        BtfIntEncoding::Char => "CHAR",
+        BtfIntEncoding::Bool => "BOOL",
+    }
+}
+
+pub(crate) fn func_linkage_name(kind: BtfFuncKind) -> &'static str {
+    match kind {
+        BtfFuncKind::Unknown => "(unknown)",
+        BtfFuncKind::Static => "static",
+        BtfFuncKind::Global => "global",
+        BtfFuncKind::Extern => "extern",
+    }
+}
+
+pub(crate) fn var_linkage_name(kind: BtfVarKind) -> &'static str {
+    match kind {
+        BtfVarKind::Static => "static",
+        BtfVarKind::GlobalAlloc => "global",
+        BtfVarKind::GlobalExtern => "extern",
+    }
+}
+
+impl<'a> Btf<'a> {
+    /// Describes all types (except void) as JSON document of the form `{"types": [...]}`, with
+    /// the same layout as produced by `bpftool btf dump file <file> -j`: each type is an object
+    /// with its ID, kind, name, and kind-specific fields, referencing other types by ID.
+    pub fn to_json(&self) -> String {
+        self.types_to_json(1..self.type_cnt(), false)
+    }
+
+    /// Same as `to_json()`, but indented for human consumption.
+    pub fn to_json_pretty(&self) -> String {
+        self.types_to_json(1..self.type_cnt(), true)
+    }
+
+    /// Describes only types with given IDs as JSON, see `to_json()`.
+    pub fn types_to_json<I: IntoIterator<Item = u32>>(&self, ids: I, pretty: bool) -> String {
+        let types = ids.into_iter().map(|id| self.type_json(id)).collect();
+        let doc = Json::Obj(vec![("types", Json::Arr(types))]);
+        let mut out = String::new();
+        doc.write(&mut out, pretty, 0);
+        out
+    }
+
+    fn type_json(&self, id: u32) -> Json<'_> {
+        let t = self.type_by_id(id);
+        let mut obj = vec![
+            ("id", num(id)),
+            ("kind", Json::Str(kind_name(t))),
+            ("name", Json::Str(t.name())),
+        ];
+        match t {
+            BtfType::Void => {}
+            BtfType::Int(t) => {
+                obj.push(("size", num(t.sz)));
+                obj.push(("bits_offset", num(t.offset)));
+                obj.push(("nr_bits", num(t.bits)));
+                obj.push(("encoding", Json::Str(int_encoding_name(t.encoding))));
+            }
+            BtfType::Ptr(t) => obj.push(("type_id", num(t.type_id))),
+            BtfType::Array(t) => {
+                obj.push(("type_id", num(t.val_type_id)));
+                obj.push(("index_type_id", num(t.idx_type_id)));
+                obj.push(("nr_elems", num(t.nelems)));
+            }
+            BtfType::Struct(t) | BtfType::Union(t) => {
+                obj.push(("size", num(t.sz)));
+                obj.push(("vlen", num(t.members.len() as u32)));
+                let members = t
+                    .members
+                    .iter()
+                    .map(|m| {
+                        let mut mobj = vec![
+                            ("name", Json::Str(m.name)),
+                            ("type_id", num(m.type_id)),
+                            ("bits_offset", num(m.bit_offset)),
+                        ];
+                        if m.bit_size != 0 {
+                            mobj.push(("bitfield_size", num(m.bit_size)));
+                        }
+                        Json::Obj(mobj)
+                    })
+                    .collect();
+                obj.push(("members", Json::Arr(members)));
+            }
+            BtfType::Enum(t) | BtfType::Enum64(t) => {
+                let enc = if t.is_signed { "SIGNED" } else { "UNSIGNED" };
+                obj.push(("encoding", Json::Str(enc)));
+                obj.push(("size", num(t.sz)));
+                obj.push(("vlen", num(t.values.len() as u32)));
+                let values = t
+                    .values
+                    .iter()
+                    .map(|v| {
+                        Json::Obj(vec![
+                            ("name", Json::Str(v.name)),
+                            ("val", Json::Num(t.value_of(v))),
+                        ])
+                    })
+                    .collect();
+                obj.push(("values", Json::Arr(values)));
+            }
+            BtfType::Fwd(t) => {
+                let kind = match t.kind {
+                    BtfFwdKind::Struct => "struct",
+                    BtfFwdKind::Union => "union",
+                };
+                obj.push(("fwd_kind", Json::Str(kind)));
+            }
+            BtfType::Typedef(t) => obj.push(("type_id", num(t.type_id))),
+            BtfType::Volatile(t) => obj.push(("type_id", num(t.type_id))),
+            BtfType::Const(t) => obj.push(("type_id", num(t.type_id))),
+            BtfType::Restrict(t) => obj.push(("type_id", num(t.type_id))),
+            BtfType::Func(t) => {
+                obj.push(("type_id", num(t.proto_type_id)));
+                obj.push(("linkage", Json::Str(func_linkage_name(t.kind))));
+            }
+            BtfType::FuncProto(t) => {
+                obj.push(("ret_type_id", num(t.res_type_id)));
+                obj.push(("vlen", num(t.params.len() as u32)));
+                let params = t
+                    .params
+                    .iter()
+                    .map(|p| {
+                        Json::Obj(vec![
+                            ("name", Json::Str(p.name)),
+                            ("type_id", num(p.type_id)),
+                        ])
+                    })
+                    .collect();
+                obj.push(("params", Json::Arr(params)));
+            }
+            BtfType::Var(t) => {
+                obj.push(("type_id", num(t.type_id)));
+                obj.push(("linkage", Json::Str(var_linkage_name(t.kind))));
+            }
+            BtfType::Datasec(t) => {
+                obj.push(("size", num(t.sz)));
+                obj.push(("vlen", num(t.vars.len() as u32)));
+                let vars = t
+                    .vars
+                    .iter()
+                    .map(|v| {
+                        Json::Obj(vec![
+                            ("type_id", num(v.type_id)),
+                            ("offset", num(v.offset)),
+                            ("size", num(v.sz)),
+                        ])
+                    })
+                    .collect();
+                obj.push(("vars", Json::Arr(vars)));
+            }
+            BtfType::Float(t) => obj.push(("size", num(t.sz))),
+            BtfType::DeclTag(t) => {
+                obj.push(("type_id", num(t.type_id)));
+                obj.push(("component_idx", num(t.comp_idx)));
+            }
+            BtfType::TypeTag(t) => obj.push(("type_id", num(t.type_id))),
+        }
+        Json::Obj(obj)
+    }
+}
@@ -0,0 +1,338 @@
+use std::cmp::max;
+use std::fmt;
+
+use crate::types::*;
+use crate::{btf_error, BtfResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberLayout<'a> {
+    pub name: &'a str,
+    pub type_id: u32,
+    pub bit_offset: u32,
+    /// number of bits member occupies, for non-bitfield members it's the size of its type
+    pub bit_size: u32,
+    pub is_bitfield: bool,
+}
+
+impl<'a> MemberLayout<'a> {
+    pub fn bit_end(&self) -> u32 {
+        self.bit_offset + self.bit_size
+    }
+}
+
+/// Unused gap between members of a struct
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Hole {
+    pub bit_offset: u32,
+    pub bit_size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLayout<'a> {
+    pub id: u32,
+    pub name: &'a str,
+    pub is_struct: bool,
+    pub sz: u32,
+    pub align: u32,
+    pub members: Vec<MemberLayout<'a>>,
+    /// gaps between members, in the order of their offset
+    pub holes: Vec<Hole>,
+    /// unused bits after the last member up to the end of struct
+    pub tail_padding_bits: u32,
+}
+
+impl<'a> StructLayout<'a> {
+    /// Total number of bits not used by any of the members
+    pub fn padding_bits(&self) -> u32 {
+        self.holes.iter().map(|h| h.bit_size).sum::<u32>() + self.tail_padding_bits
+    }
+}
+
+impl<'a> fmt::Display for StructLayout<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} sz:{} align:{}",
+            if self.is_struct { "struct" } else { "union" },
+            if self.name.is_empty() {
+                "<anon>"
+            } else {
+                self.name
+            },
+            self.sz,
+            self.align
+        )?;
+        let mut holes = self.holes.iter().peekable();
+        for m in &self.members {
+            while let Some(h) = holes.next_if(|h| h.bit_offset < m.bit_offset) {
+                write!(
+                    f,
+                    "\n\t/* hole: off:{} bits:{} */",
+                    h.bit_offset, h.bit_size
+                )?;
+            }
+            write!(
+                f,
+                "\n\t'{}' off:{} bits:{}",
+                if m.name.is_empty() { "<anon>" } else { m.name },
+                m.bit_offset,
+                m.bit_size
+            )?;
+        }
+        if self.tail_padding_bits > 0 {
+            write!(f, "\n\t/* padding: bits:{} */", self.tail_padding_bits)?;
+        }
+        Ok(())
+    }
+}
+
+/// Member placement with respect to cache lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachelineMember<'a> {
+    pub name: &'a str,
+    pub bit_offset: u32,
+    pub bit_size: u32,
+    pub first_line: u32,
+    pub last_line: u32,
+}
+
+impl<'a> CachelineMember<'a> {
+    /// Whether member spans more than one cache line
+    pub fn straddles(&self) -> bool {
+        self.first_line != self.last_line
+    }
+}
+
+/// Expected size, alignment, and byte offsets of named fields of a struct/union, usually taken
+/// from its `#[repr(C)]` Rust mirror with `expected_layout!()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedLayout {
+    pub sz: u32,
+    pub align: u32,
+    pub fields: Vec<(&'static str, u32)>,
+}
+
+/// Captures `ExpectedLayout` of a Rust type, e.g., `expected_layout!(Foo { a, b, c })`.
+#[macro_export]
+macro_rules! expected_layout {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        $crate::layout::ExpectedLayout {
+            sz: ::std::mem::size_of::<$ty>() as u32,
+            align: ::std::mem::align_of::<$ty>() as u32,
+            fields: vec![$((stringify!($field), ::std::mem::offset_of!($ty, $field) as u32)),*],
+        }
+    };
+}
+
+impl<'a> Btf<'a> {
+    pub fn struct_layout(&self, id: u32) -> BtfResult<StructLayout<'_>> {
+        let t = match self.type_by_id_opt(id) {
+            Some(BtfType::Struct(t)) | Some(BtfType::Union(t)) => t,
+            None => return btf_error(format!("Type ID {} is out of bounds", id)),
+            Some(t) => {
+                return btf_error(format!(
+                    "Expected struct or union, but got id: {}, type: {}",
+                    id, t
+                ))
+            }
+        };
+        let mut layout = StructLayout {
+            id,
+            name: t.name,
+            is_struct: t.is_struct,
+            sz: t.sz,
+            align: self.get_align_of(id),
+            members: Vec::new(),
+            holes: Vec::new(),
+            tail_padding_bits: 0,
+        };
+        let mut end = 0;
+        for m in &t.members {
+            let zero_width = self.is_zero_width_bitfield(m);
+            // int not filling its whole storage is effectively a bitfield
+            let part_int = match self.type_by_id(m.type_id) {
+                BtfType::Int(int) if m.bit_size == 0 && !int.is_full_width() => Some(int),
+                _ => None,
+            };
+            let ml = MemberLayout {
+                name: m.name,
+                type_id: m.type_id,
+                bit_offset: m.bit_offset + part_int.map_or(0, |int| int.offset),
+                bit_size: if let Some(int) = part_int {
+                    int.bits
+                } else if m.bit_size == 0 && !zero_width {
+                    self.get_size_of(m.type_id)?.saturating_mul(8)
+                } else {
+                    m.bit_size as u32
+                },
+                is_bitfield: m.bit_size != 0 || zero_width || part_int.is_some(),
+            };
+            if t.is_struct && ml.bit_offset > end {
+                layout.holes.push(Hole {
+                    bit_offset: end,
+                    bit_size: ml.bit_offset - end,
+                });
+            }
+            end = max(end, ml.bit_end());
+            layout.members.push(ml);
+        }
+        layout.tail_padding_bits = (t.sz * 8).saturating_sub(end);
+        Ok(layout)
+    }
+
+    /// Padding bytes of each struct with any padding at all, as (type ID, bytes) pairs, sorted
+    /// from the most wasteful struct to the least one.
+    pub fn padding_by_struct(&self) -> Vec<(u32, u32)> {
+        let mut res: Vec<(u32, u32)> = self
+            .iter_kind(BtfKind::Struct)
+            .filter_map(|(id, _)| self.struct_layout(id).ok())
+            .map(|l| (l.id, l.padding_bits() / 8))
+            .filter(|&(_, bytes)| bytes > 0)
+            .collect();
+        res.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        res
+    }
+
+    /// Total number of padding bytes in all structs
+    pub fn total_padding(&self) -> u64 {
+        self.padding_by_struct()
+            .iter()
+            .map(|&(_, bytes)| bytes as u64)
+            .sum()
+    }
+
+    /// Reports which cache line(s) of a given size each member of a struct/union occupies.
+    pub fn cacheline_report(&self, id: u32, line_sz: u32) -> BtfResult<Vec<CachelineMember<'_>>> {
+        if line_sz == 0 {
+            return btf_error("Cache line size can't be zero".to_owned());
+        }
+        let layout = self.struct_layout(id)?;
+        let line_bits = line_sz * 8;
+        Ok(layout
+            .members
+            .iter()
+            .map(|m| CachelineMember {
+                name: m.name,
+                bit_offset: m.bit_offset,
+                bit_size: m.bit_size,
+                first_line: m.bit_offset / line_bits,
+                last_line: max(m.bit_offset, m.bit_end().saturating_sub(1)) / line_bits,
+            })
+            .collect())
+    }
+
+    /// Checks that struct/union with given ID has expected layout, failing with an error
+    /// describing the first mismatch found. Fields are matched by name, so expected layout
+    /// has to list every named field of BTF type, but in any order.
+    pub fn assert_layout(&self, id: u32, expected: &ExpectedLayout) -> BtfResult<()> {
+        let layout = self.struct_layout(id)?;
+        let name = self.full_name(id);
+        if layout.sz != expected.sz {
+            return btf_error(format!(
+                "Layout mismatch for {} (id {}): size is {}, expected {}",
+                name, id, layout.sz, expected.sz
+            ));
+        }
+        if layout.align != expected.align {
+            return btf_error(format!(
+                "Layout mismatch for {} (id {}): alignment is {}, expected {}",
+                name, id, layout.align, expected.align
+            ));
+        }
+        for m in layout.members.iter().filter(|m| !m.name.is_empty()) {
+            let off = match expected.fields.iter().find(|(f, _)| *f == m.name) {
+                Some((_, off)) => *off,
+                None => {
+                    return btf_error(format!(
+                        "Layout mismatch for {} (id {}): field '{}' is not expected",
+                        name, id, m.name
+                    ))
+                }
+            };
+            if m.is_bitfield || m.bit_offset % 8 != 0 {
+                return btf_error(format!(
+                    "Layout mismatch for {} (id {}): field '{}' is a bitfield at bit offset {}",
+                    name, id, m.name, m.bit_offset
+                ));
+            }
+            if m.bit_offset / 8 != off {
+                return btf_error(format!(
+                    "Layout mismatch for {} (id {}): field '{}' is at offset {}, expected {}",
+                    name,
+                    id,
+                    m.name,
+                    m.bit_offset / 8,
+                    off
+                ));
+            }
+        }
+        if let Some((f, _)) = expected
+            .fields
+            .iter()
+            .find(|(f, _)| !layout.members.iter().any(|m| m.name == *f))
+        {
+            return btf_error(format!(
+                "Layout mismatch for {} (id {}): expected field '{}' is missing",
+                name, id, f
+            ));
+        }
+        Ok(())
+    }
+
+    /// Resolves dotted path of member names (e.g., `mm.pgd`) starting from struct/union with
+    /// given ID and returns member's bit offset from the start of that struct/union. Members of
+    /// anonymous embedded structs/unions are found as if they were members of enclosing type,
+    /// just like in C. Each intermediate member has to be a struct/union itself (modifiers and
+    /// typedefs are skipped), pointers are not followed.
+    pub fn member_offset(&self, struct_id: u32, field_path: &str) -> BtfResult<u32> {
+        if struct_id >= self.type_cnt() {
+            return btf_error(format!("Type ID {} is out of bounds", struct_id));
+        }
+        let mut id = struct_id;
+        let mut off = 0;
+        for name in field_path.split('.') {
+            let members = match self.type_by_id(self.skip_mods_and_typedefs(id)) {
+                BtfType::Struct(t) | BtfType::Union(t) => &t.members,
+                t => {
+                    return btf_error(format!(
+                        "Can't resolve '{}' of '{}': type [{}] is not a struct or union: {}",
+                        name, field_path, id, t
+                    ))
+                }
+            };
+            match self.find_member(members, name) {
+                Some((type_id, bit_offset)) => {
+                    id = type_id;
+                    off += bit_offset;
+                }
+                None => {
+                    return btf_error(format!(
+                        "Can't resolve '{}' of '{}': no such member in type [{}]",
+                        name, field_path, id
+                    ))
+                }
+            }
+        }
+        Ok(off)
+    }
+
+    /// Looks up member by name, descending into anonymous struct/union members. Returns
+    /// member's type ID and bit offset.
+    fn find_member(&self, members: &[BtfMember], name: &str) -> Option<(u32, u32)> {
+        for m in members {
+            if m.name == name {
+                return Some((m.type_id, m.bit_offset));
+            }
+            if m.name.is_empty() {
+                if let BtfType::Struct(t) | BtfType::Union(t) =
+                    self.type_by_id(self.skip_mods_and_typedefs(m.type_id))
+                {
+                    if let Some((type_id, off)) = self.find_member(&t.members, name) {
+                        return Some((type_id, m.bit_offset + off));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
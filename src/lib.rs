@@ -1,36 +1,152 @@
 use std::error::Error;
 use std::fmt;
 
+#[cfg(not(any(feature = "regex", feature = "no-regex")))]
+compile_error!("either `regex` (default) or `no-regex` feature has to be enabled");
+
 pub mod btf_index;
+pub mod builder;
 pub mod c_dumper;
+pub mod canonical;
+pub mod dedup;
+pub mod diff;
+pub mod json;
+pub mod layout;
+pub mod raw;
 pub mod relocator;
+pub mod stats;
+pub mod strings;
 pub mod types;
+pub mod validate;
+pub mod writer;
 
+/// Errors produced while loading and processing BTF. Callers get them boxed in `BtfResult`, so
+/// `err.downcast_ref::<BtfError>()` lets them tell specific failures apart.
 #[derive(Debug)]
-pub struct BtfError {
-    details: String,
+#[non_exhaustive]
+pub enum BtfError {
+    /// BTF or .BTF.ext header doesn't start with BTF magic
+    InvalidMagic(u16),
+    UnsupportedVersion(u8),
+    /// ELF section with given name is not present
+    MissingSection(String),
+    /// type data at given offset within type section is cut short
+    TruncatedType {
+        offset: usize,
+    },
+    /// type or string section, as described by BTF header, extends past the end of BTF data
+    SectionOutOfBounds {
+        section: &'static str,
+        end: usize,
+        data_len: usize,
+    },
+    /// records of .BTF.ext info section (e.g., `func info`) starting at given offset within it
+    /// extend past the end of its data
+    TruncatedExtInfo {
+        info: &'static str,
+        offset: usize,
+    },
+    /// string offset is out of bounds of string section
+    BadStringOffset(u32),
+    /// string at given offset runs to the end of string section without a NUL terminator
+    UnterminatedString(u32),
+    /// string at given offset isn't valid UTF-8
+    InvalidString(u32),
+    UnknownKind(u32),
+    /// type with ID `id` references type ID `type_id`, which is out of range
+    BadTypeId {
+        id: u32,
+        type_id: u32,
+    },
+    /// type with given ID is part of a type cycle that can't be broken
+    TypeCycle {
+        id: u32,
+    },
+    Io(std::io::Error),
+    /// malformed ELF or raw BTF data
+    Parse(Box<dyn Error + Send + Sync>),
+    /// anything else, described in free form
+    Message(String),
 }
 
 impl BtfError {
     pub fn new(msg: &str) -> BtfError {
-        BtfError {
-            details: msg.to_string(),
-        }
+        BtfError::Message(msg.to_string())
     }
     pub fn new_owned(msg: String) -> BtfError {
-        BtfError { details: msg }
+        BtfError::Message(msg)
     }
 }
 
 impl fmt::Display for BtfError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
+        match self {
+            BtfError::InvalidMagic(magic) => write!(f, "Invalid BTF magic: {:#x}", magic),
+            BtfError::UnsupportedVersion(ver) => write!(f, "Unsupported BTF version: {}", ver),
+            BtfError::MissingSection(name) => write!(f, "No {} section found!", name),
+            BtfError::TruncatedType { offset } => {
+                write!(f, "Truncated BTF type data at offset {}", offset)
+            }
+            BtfError::SectionOutOfBounds {
+                section,
+                end,
+                data_len,
+            } => write!(
+                f,
+                "BTF {} section ends at offset {}, past the end of {}-byte BTF data",
+                section, end, data_len
+            ),
+            BtfError::TruncatedExtInfo { info, offset } => {
+                write!(
+                    f,
+                    "Truncated .BTF.ext {} records at offset {}",
+                    info, offset
+                )
+            }
+            BtfError::BadStringOffset(off) => write!(f, "Invalid BTF string offset: {}", off),
+            BtfError::UnterminatedString(off) => {
+                write!(f, "BTF string at offset {} is not NUL-terminated", off)
+            }
+            BtfError::InvalidString(off) => {
+                write!(f, "BTF string at offset {} is not valid UTF-8", off)
+            }
+            BtfError::UnknownKind(kind) => write!(f, "Unknown BTF kind: {}", kind),
+            BtfError::BadTypeId { id, type_id } => {
+                write!(f, "Type [{}] references invalid type ID: {}", id, type_id)
+            }
+            BtfError::TypeCycle { id } => write!(f, "Unsatisfiable type cycle, id: {}", id),
+            BtfError::Io(e) => write!(f, "I/O error: {}", e),
+            BtfError::Parse(e) => write!(f, "Parse error: {}", e),
+            BtfError::Message(msg) => write!(f, "{}", msg),
+        }
     }
 }
 
 impl Error for BtfError {
-    fn description(&self) -> &str {
-        &self.details
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BtfError::Io(e) => Some(e),
+            BtfError::Parse(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BtfError {
+    fn from(e: std::io::Error) -> BtfError {
+        BtfError::Io(e)
+    }
+}
+
+impl From<scroll::Error> for BtfError {
+    fn from(e: scroll::Error) -> BtfError {
+        BtfError::Parse(Box::new(e))
+    }
+}
+
+impl From<object::Error> for BtfError {
+    fn from(e: object::Error) -> BtfError {
+        BtfError::Parse(Box::new(e))
     }
 }
 
@@ -1,11 +1,11 @@
 use std::collections::BTreeMap;
-use std::collections::HashMap;
 use std::error::Error;
 use std::io::Write;
 
 use bitflags::bitflags;
 use memmap;
 use object::{Object, ObjectSection};
+#[cfg(not(feature = "no-regex"))]
 use regex::Regex;
 use scroll::Pread;
 use std::mem::size_of;
@@ -16,11 +16,12 @@ use btf::relocator::{Relocator, RelocatorCfg};
 use btf::types::*;
 use btf::{btf_error, BtfError, BtfResult};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum DumpFormat {
     Human,
     Json,
     JsonPretty,
+    Raw,
     C,
 }
 
@@ -32,6 +33,7 @@ impl std::str::FromStr for DumpFormat {
             "human" | "h" => Ok(DumpFormat::Human),
             "json" | "j" => Ok(DumpFormat::Json),
             "json-pretty" | "jp" => Ok(DumpFormat::JsonPretty),
+            "raw" | "r" => Ok(DumpFormat::Raw),
             "c" => Ok(DumpFormat::C),
             _ => Err(BtfError::new_owned(format!(
                 "unrecognized dump format: '{}'",
@@ -104,11 +106,15 @@ enum Cmd {
     Dump {
         #[structopt(parse(from_os_str))]
         file: std::path::PathBuf,
+        #[structopt(long = "base", parse(from_os_str))]
+        /// Base BTF (e.g., /sys/kernel/btf/vmlinux) to load split BTF (e.g., module's) on top of;
+        /// only split BTF's own types are dumped, unless --min-id is specified
+        base: Option<std::path::PathBuf>,
         #[structopt(
             short = "f",
             long = "format",
             default_value = "human",
-            possible_values = &["human", "h", "c", "json", "j", "json-pretty", "jp"],
+            possible_values = &["human", "h", "c", "json", "j", "json-pretty", "jp", "raw", "r"],
         )]
         /// Output format
         format: DumpFormat,
@@ -128,6 +134,63 @@ enum Cmd {
         #[structopt(long = "union-as-struct")]
         /// Replace unions with structs (for BPF CORE)
         union_as_struct: bool,
+        #[structopt(long = "min-id", default_value = "0")]
+        /// Skip types with IDs below this one, assuming they are declared elsewhere
+        min_id: u32,
+        #[structopt(long = "extern-c")]
+        /// Wrap C output in extern "C" block for C++ consumers
+        extern_c: bool,
+        #[structopt(long = "vars")]
+        /// Emit var declarations grouped by data section (C format only)
+        vars: bool,
+        #[structopt(long = "funcs")]
+        /// Emit extern prototypes of functions (C format only)
+        funcs: bool,
+        #[structopt(long = "cachelines")]
+        /// Annotate struct definitions with cache line boundaries (C format only)
+        cachelines: bool,
+        #[structopt(long = "cacheline-size", default_value = "64")]
+        /// Cache line size in bytes, used with --cachelines
+        cacheline_size: u32,
+        #[structopt(long = "field-lists")]
+        /// Emit comment listing member names before each struct/union (C format only)
+        field_lists: bool,
+        #[structopt(long = "field-offsets")]
+        /// Annotate struct/union members with their offsets and sizes (C format only)
+        field_offsets: bool,
+        #[structopt(long = "max-types", default_value = "0")]
+        /// Stop after emitting this many type definitions, 0 means no limit (C format only)
+        max_types: usize,
+        #[structopt(long = "anon-type-ids")]
+        /// Annotate inlined anonymous types with their BTF type IDs (C format only)
+        anon_type_ids: bool,
+        #[structopt(long = "stable-names")]
+        /// Assign ___N suffixes to conflicting names in type ID order, independent of filters
+        stable_names: bool,
+        #[structopt(long = "vmlinux-h")]
+        /// Follow conventions of libbpf-generated vmlinux.h (C format only)
+        vmlinux_h: bool,
+        #[structopt(long = "group-by-file")]
+        /// Group types by source file, based on .BTF.ext line info (C format only)
+        group_by_file: bool,
+        #[structopt(long = "header-guard")]
+        /// Wrap output in include guard derived from given name, e.g., vmlinux (C format only)
+        header_guard: Option<String>,
+        #[structopt(long = "std-includes")]
+        /// Include <stdint.h>/<stdbool.h>, if needed by int types (C format only)
+        std_includes: bool,
+        #[structopt(long = "closure")]
+        /// Emit only matching types and types their definitions depend on (C format only)
+        closure: bool,
+        #[structopt(long = "indent")]
+        /// Indent with given number of spaces instead of tabs (C format only)
+        indent: Option<usize>,
+        #[structopt(long = "anon-typedefs")]
+        /// Define anonymous structs/unions as typedefs named __anon_<id> (C format only)
+        anon_typedefs: bool,
+        #[structopt(long = "fixed-width-ints")]
+        /// Emit ints as <stdint.h> types of the same width, e.g., uint64_t (C format only)
+        fixed_width_ints: bool,
     },
     #[structopt(name = "reloc")]
     /// Print detailed relocation information
@@ -156,23 +219,54 @@ fn main() -> Result<(), Box<dyn Error>> {
     match cmd {
         Cmd::Dump {
             file,
+            base,
             format,
             datasets,
             query,
             verbose,
             union_as_struct,
+            mut min_id,
+            extern_c,
+            vars,
+            funcs,
+            cachelines,
+            cacheline_size,
+            field_lists,
+            field_offsets,
+            max_types,
+            anon_type_ids,
+            stable_names,
+            vmlinux_h,
+            group_by_file,
+            header_guard,
+            std_includes,
+            closure,
+            indent,
+            anon_typedefs,
+            fixed_width_ints,
         } => {
-            let file = std::fs::File::open(&file)?;
-            let file = unsafe { memmap::Mmap::map(&file) }?;
-            let file = object::File::parse(&*file)?;
-            let btf = Btf::load(&file)?;
+            let base_btf = match base {
+                Some(base) => Some(Btf::from_path(&base)?),
+                None => None,
+            };
+            let owned_btf = match &base_btf {
+                Some(base) => {
+                    let btf = Btf::from_path_split(base.btf(), &file)?;
+                    if min_id == 0 {
+                        min_id = btf.btf().base_type_cnt();
+                    }
+                    btf
+                }
+                None => Btf::from_path(&file)?,
+            };
+            let btf = owned_btf.btf();
             let filter = create_query_filter(query)?;
 
             match format {
                 DumpFormat::Human => {
                     if datasets.contains(Datasets::TYPES) {
-                        for (i, t) in btf.types().iter().enumerate() {
-                            if filter(i as u32, t) {
+                        for (i, t) in btf.iter() {
+                            if i >= min_id && filter(i, t) {
                                 println!("#{}: {}", i, t);
                             }
                         }
@@ -199,7 +293,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                             for (j, rec) in sec.recs.iter().enumerate() {
                                 print!("#{}: {} --> ", j, rec);
                                 std::io::stdout().flush()?;
-                                match Relocator::pretty_print_access_spec(&btf, rec) {
+                                match Relocator::pretty_print_access_spec(btf, rec) {
                                     Ok(s) => print!("{}", s),
                                     Err(e) => print!(" ERROR: {}", e),
                                 };
@@ -208,15 +302,77 @@ fn main() -> Result<(), Box<dyn Error>> {
                         }
                     }
                 }
-                DumpFormat::Json => panic!("JSON output is not yet supported!"),
-                DumpFormat::JsonPretty => panic!("JSON output is not yet supported!"),
+                DumpFormat::Json | DumpFormat::JsonPretty => {
+                    let ids = btf
+                        .iter()
+                        .filter(|&(id, t)| id > 0 && id >= min_id && filter(id, t))
+                        .map(|(id, _)| id);
+                    println!(
+                        "{}",
+                        btf.types_to_json(ids, format == DumpFormat::JsonPretty)
+                    );
+                }
+                DumpFormat::Raw => {
+                    let mut out = std::io::stdout().lock();
+                    for (id, t) in btf.iter() {
+                        if id > 0 && id >= min_id && filter(id, t) {
+                            btf.dump_raw_type(&mut out, id)?;
+                        }
+                    }
+                }
                 DumpFormat::C => {
-                    let cfg = c_dumper::CDumperCfg {
+                    let mut cfg = c_dumper::CDumperCfg {
                         verbose: verbose,
                         union_as_struct: union_as_struct,
+                        min_id,
+                        extern_c,
+                        cacheline_sz: if cachelines { cacheline_size } else { 0 },
+                        field_lists,
+                        field_offsets,
+                        max_types,
+                        anon_type_ids,
+                        stable_names,
+                        group_by_file,
+                        std_includes,
+                        anon_typedefs,
+                        indent: indent.map_or(c_dumper::Indent::Tabs, c_dumper::Indent::Spaces),
+                        ..if vmlinux_h {
+                            c_dumper::CDumperCfg::vmlinux_preset()
+                        } else {
+                            Default::default()
+                        }
                     };
-                    let mut dumper = c_dumper::CDumper::new(&btf, cfg);
-                    dumper.dump_types(filter)?;
+                    if let Some(name) = header_guard {
+                        cfg = cfg.with_header_guard(&name);
+                    }
+                    if fixed_width_ints {
+                        cfg = cfg.with_fixed_width_ints();
+                    }
+                    let out = std::io::BufWriter::new(std::io::stdout().lock());
+                    let mut dumper = c_dumper::CDumper::new(btf, out, cfg);
+                    dumper.emit_prologue()?;
+                    if closure {
+                        for (id, t) in btf.iter() {
+                            if id > 0 && filter(id, t) {
+                                dumper.dump_type_closure(id)?;
+                            }
+                        }
+                    } else {
+                        dumper.dump_types(filter)?;
+                    }
+                    if vars {
+                        dumper.dump_datasecs(Box::new(|_: u32, _: &BtfType| true))?;
+                    }
+                    if funcs {
+                        dumper.dump_funcs(Box::new(|_: u32, _: &BtfType| true))?;
+                    }
+                    dumper.emit_epilogue()?;
+                    if verbose {
+                        for s in dumper.skipped() {
+                            eprintln!("SKIPPED id: {}, reason: {}", s.id, s.reason);
+                        }
+                    }
+                    dumper.into_writer().flush()?;
                 }
             }
         }
@@ -271,9 +427,16 @@ fn create_query_filter(q: QueryArgs) -> BtfResult<Box<dyn Fn(u32, &BtfType) -> b
         }));
     }
     if let Some(name) = q.name {
-        let name_regex = Regex::new(&name)?;
+        #[cfg(not(feature = "no-regex"))]
+        {
+            let name_regex = Regex::new(&name)?;
+            filters.push(Box::new(move |_id: u32, bt: &BtfType| -> bool {
+                name_regex.is_match(bt.name())
+            }));
+        }
+        #[cfg(feature = "no-regex")]
         filters.push(Box::new(move |_id: u32, bt: &BtfType| -> bool {
-            name_regex.is_match(bt.name())
+            bt.name() == name
         }));
     }
     if !filters.is_empty() {
@@ -332,28 +495,8 @@ fn stat_btf(elf: &object::File) -> BtfResult<()> {
     match Btf::load(elf) {
         Err(e) => println!("Failed to parse BTF data: {}", e),
         Ok(btf) => {
-            let mut type_stats: HashMap<BtfKind, (usize, usize)> = HashMap::new();
-            for t in &btf.types()[1..] {
-                let (cnt, sz) = type_stats.entry(t.kind()).or_insert((0, 0));
-                *cnt += 1;
-                *sz += Btf::type_size(t);
-            }
-            let mut total_cnt = 0;
-            let mut total_sz = 0;
-            for (cnt, sz) in type_stats.values() {
-                total_cnt += cnt;
-                total_sz += sz;
-            }
-            let mut type_stats = type_stats
-                .into_iter()
-                .map(|(k, (cnt, sz))| (k, cnt, sz))
-                .collect::<Vec<(BtfKind, usize, usize)>>();
-            type_stats.sort_by_key(|&(_, _, sz)| std::cmp::Reverse(sz));
             println!("\nBTF types\n=======================================");
-            println!("{:10} {:9} bytes ({} types)", "Total", total_sz, total_cnt);
-            for (k, cnt, sz) in type_stats {
-                println!("{:10} {:9} bytes ({} types)", format!("{:?}:", k), sz, cnt);
-            }
+            println!("{}", btf.stats());
 
             if btf.has_ext() {
                 struct Section {
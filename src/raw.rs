@@ -0,0 +1,108 @@
+use std::io::{self, Write};
+
+use crate::json::{func_linkage_name, int_encoding_name, kind_name, var_linkage_name};
+use crate::types::*;
+
+fn raw_name(name: &str) -> &str {
+    if name.is_empty() {
+        "(anon)"
+    } else {
+        name
+    }
+}
+
+impl<'a> Btf<'a> {
+    /// Writes all types (except void) in the same format as `bpftool btf dump file <file>
+    /// format raw` does, e.g., `[1] INT 'int' size=4 bits_offset=0 nr_bits=32 encoding=SIGNED`,
+    /// so that outputs can be diffed against each other.
+    pub fn dump_raw<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for id in 1..self.type_cnt() {
+            self.dump_raw_type(writer, id)?;
+        }
+        Ok(())
+    }
+
+    /// Writes one type in bpftool's raw format, see `dump_raw()`.
+    pub fn dump_raw_type<W: Write>(&self, w: &mut W, id: u32) -> io::Result<()> {
+        let t = self.type_by_id(id);
+        write!(w, "[{}] {} '{}'", id, kind_name(t), raw_name(t.name()))?;
+        match t {
+            BtfType::Void => {}
+            BtfType::Int(t) => write!(
+                w,
+                " size={} bits_offset={} nr_bits={} encoding={}",
+                t.sz,
+                t.offset,
+                t.bits,
+                int_encoding_name(t.encoding)
+            )?,
+            BtfType::Ptr(t) => write!(w, " type_id={}", t.type_id)?,
+            BtfType::Array(t) => write!(
+                w,
+                " type_id={} index_type_id={} nr_elems={}",
+                t.val_type_id, t.idx_type_id, t.nelems
+            )?,
+            BtfType::Struct(t) | BtfType::Union(t) => {
+                write!(w, " size={} vlen={}", t.sz, t.members.len())?;
+                for m in &t.members {
+                    write!(
+                        w,
+                        "\n\t'{}' type_id={} bits_offset={}",
+                        raw_name(m.name),
+                        m.type_id,
+                        m.bit_offset
+                    )?;
+                    if m.bit_size != 0 {
+                        write!(w, " bitfield_size={}", m.bit_size)?;
+                    }
+                }
+            }
+            BtfType::Enum(t) | BtfType::Enum64(t) => {
+                let enc = if t.is_signed { "SIGNED" } else { "UNSIGNED" };
+                write!(w, " encoding={} size={} vlen={}", enc, t.sz, t.values.len())?;
+                for v in &t.values {
+                    write!(w, "\n\t'{}' val={}", raw_name(v.name), t.value_of(v))?;
+                }
+            }
+            BtfType::Fwd(t) => write!(w, " fwd_kind={}", t.kind)?,
+            BtfType::Typedef(t) => write!(w, " type_id={}", t.type_id)?,
+            BtfType::Volatile(t) => write!(w, " type_id={}", t.type_id)?,
+            BtfType::Const(t) => write!(w, " type_id={}", t.type_id)?,
+            BtfType::Restrict(t) => write!(w, " type_id={}", t.type_id)?,
+            BtfType::Func(t) => write!(
+                w,
+                " type_id={} linkage={}",
+                t.proto_type_id,
+                func_linkage_name(t.kind)
+            )?,
+            BtfType::FuncProto(t) => {
+                write!(w, " ret_type_id={} vlen={}", t.res_type_id, t.params.len())?;
+                for p in &t.params {
+                    write!(w, "\n\t'{}' type_id={}", raw_name(p.name), p.type_id)?;
+                }
+            }
+            BtfType::Var(t) => write!(
+                w,
+                " type_id={}, linkage={}",
+                t.type_id,
+                var_linkage_name(t.kind)
+            )?,
+            BtfType::Datasec(t) => {
+                write!(w, " size={} vlen={}", t.sz, t.vars.len())?;
+                for v in &t.vars {
+                    write!(
+                        w,
+                        "\n\ttype_id={} offset={} size={}",
+                        v.type_id, v.offset, v.sz
+                    )?;
+                }
+            }
+            BtfType::Float(t) => write!(w, " size={}", t.sz)?,
+            BtfType::DeclTag(t) => {
+                write!(w, " type_id={} component_idx={}", t.type_id, t.comp_idx)?
+            }
+            BtfType::TypeTag(t) => write!(w, " type_id={}", t.type_id)?,
+        }
+        writeln!(w)
+    }
+}
@@ -389,14 +389,20 @@ impl<'a, 'b> Relocator<'a, 'b> {
     fn are_kinds_compat(&self, local_id: u32, targ_id: u32) -> bool {
         let local_kind = self.local_btf.type_by_id(local_id).kind();
         let targ_kind = self.targ_btf.type_by_id(targ_id).kind();
-        local_kind == targ_kind || (local_kind == BtfKind::Struct && targ_kind == BtfKind::Union)
+        local_kind == targ_kind
+            || (local_kind == BtfKind::Struct && targ_kind == BtfKind::Union)
+            || matches!(
+                (local_kind, targ_kind),
+                (BtfKind::Enum, BtfKind::Enum64) | (BtfKind::Enum64, BtfKind::Enum)
+            )
     }
 
     fn type_size(btf: &Btf, type_id: u32) -> BtfResult<u32> {
         let id = btf.skip_mods_and_typedefs(type_id);
         Ok(match btf.type_by_id(id) {
             BtfType::Int(t) if t.offset == 0 && t.bits % 8 == 0 => t.bits / 8,
-            BtfType::Enum(t) => t.sz,
+            BtfType::Enum(t) | BtfType::Enum64(t) => t.sz,
+            BtfType::Float(t) => t.sz,
             BtfType::Struct(t) => t.sz,
             BtfType::Union(t) => t.sz,
             BtfType::Array(t) => t.nelems * Relocator::type_size(btf, t.val_type_id)?,
@@ -465,7 +471,7 @@ impl<'a, 'b> Relocator<'a, 'b> {
                     if t.name.is_empty() { "<anon>" } else { &t.name }
                 )?;
             }
-            BtfType::Enum(t) => {
+            BtfType::Enum(t) | BtfType::Enum64(t) => {
                 write!(
                     buf,
                     "enum {}",
@@ -481,6 +487,9 @@ impl<'a, 'b> Relocator<'a, 'b> {
             BtfType::Int(t) => {
                 write!(buf, "int {}", t.name)?;
             }
+            BtfType::Float(t) => {
+                write!(buf, "float {}", t.name)?;
+            }
             _ => spec_error(
                 spec,
                 0,
@@ -497,9 +506,9 @@ impl<'a, 'b> Relocator<'a, 'b> {
         if Relocator::relo_is_enumval_based(rec.kind) {
             id = btf.skip_mods_and_typedefs(rec.type_id);
             match btf.type_by_id(id) {
-                BtfType::Enum(t) => {
+                BtfType::Enum(t) | BtfType::Enum64(t) => {
                     let e = &t.values[spec[0]];
-                    write!(buf, "::{} = {}", &e.name, e.value)?;
+                    write!(buf, "::{} = {}", &e.name, t.value_of(e))?;
                 }
                 _ => spec_error(spec, 0, "must be enum", id, btf.type_by_id(id))?,
             }
@@ -555,6 +564,73 @@ impl<'a, 'b> Relocator<'a, 'b> {
     }
 }
 
+impl<'a> Btf<'a> {
+    /// Describes what CO-RE relocation record targets, in terms of C expression-like path
+    /// starting at relocation's type, e.g., `task_struct.mm` for field-based relocations
+    /// (`task_struct[1].mm` if the first accessor is nonzero), `enum pid_type::PIDTYPE_PID` for
+    /// enum value based ones, and just type's name for type-based ones. Members of anonymous
+    /// embedded structs/unions are listed as if they were members of enclosing type, as they
+    /// are accessed in C. Malformed access specs result in an error.
+    pub fn core_relo_target(&self, rec: &BtfExtCoreReloc) -> BtfResult<String> {
+        let spec = &rec.access_spec;
+        if rec.type_id >= self.type_cnt() {
+            return btf_error(format!("Type ID {} is out of bounds", rec.type_id));
+        }
+        if Relocator::relo_is_type_based(rec.kind) {
+            return Ok(self.full_name(rec.type_id));
+        }
+        let invalid = |idx: usize, id: u32| {
+            btf_error(format!(
+                "Invalid access spec {} at #{}: no accessor #{} in [{}] {}",
+                Relocator::spec_to_str(spec),
+                idx,
+                spec.get(idx).map_or(0, |&acc| acc),
+                id,
+                self.full_name(id)
+            ))
+        };
+        let mut id = self.skip_mods_and_typedefs(rec.type_id);
+        if Relocator::relo_is_enumval_based(rec.kind) {
+            return match (self.type_by_id(id), spec.first()) {
+                (BtfType::Enum(t), Some(&idx)) | (BtfType::Enum64(t), Some(&idx))
+                    if idx < t.values.len() =>
+                {
+                    Ok(format!("{}::{}", self.full_name(id), t.values[idx].name))
+                }
+                _ => invalid(0, id),
+            };
+        }
+        let t = self.type_by_id(rec.type_id);
+        let mut path = if t.name().is_empty() {
+            self.full_name(rec.type_id)
+        } else {
+            t.name().to_owned()
+        };
+        match spec.first() {
+            Some(0) => {}
+            Some(idx) => write!(path, "[{}]", idx)?,
+            None => return invalid(0, rec.type_id),
+        }
+        for (i, &idx) in spec.iter().enumerate().skip(1) {
+            match self.type_by_id(id) {
+                BtfType::Struct(t) | BtfType::Union(t) if idx < t.members.len() => {
+                    let m = &t.members[idx];
+                    if !m.name.is_empty() {
+                        write!(path, ".{}", m.name)?;
+                    }
+                    id = self.skip_mods_and_typedefs(m.type_id);
+                }
+                BtfType::Array(t) => {
+                    write!(path, "[{}]", idx)?;
+                    id = self.skip_mods_and_typedefs(t.val_type_id);
+                }
+                _ => return invalid(i, id),
+            }
+        }
+        Ok(path)
+    }
+}
+
 fn spec_error<T>(
     spec: &[usize],
     idx: usize,
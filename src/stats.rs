@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::types::*;
+
+/// Number of types of a given kind and bytes they take in BTF type section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindStats {
+    pub kind: BtfKind,
+    pub cnt: usize,
+    pub bytes: usize,
+}
+
+/// Breakdown of BTF contents, for figuring out what it's made of. Only own types of split BTF
+/// are accounted for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BtfStats {
+    /// per-kind stats, from the kind taking the most bytes to the least one
+    pub kinds: Vec<KindStats>,
+    pub type_cnt: usize,
+    pub type_bytes: usize,
+    pub str_bytes: usize,
+    pub named_cnt: usize,
+    pub anon_cnt: usize,
+    /// ID, name, and size of the largest struct, if there are any structs
+    pub largest_struct: Option<(u32, String, u32)>,
+}
+
+impl fmt::Display for BtfStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:16} {:9} bytes ({} types)",
+            "Total", self.type_bytes, self.type_cnt
+        )?;
+        for k in &self.kinds {
+            write!(
+                f,
+                "\n{:16} {:9} bytes ({} types)",
+                format!("{:?}:", k.kind),
+                k.bytes,
+                k.cnt
+            )?;
+        }
+        write!(f, "\n{:16} {:9} bytes", "Strings:", self.str_bytes)?;
+        write!(f, "\n{:16} {:9}", "Named types:", self.named_cnt)?;
+        write!(f, "\n{:16} {:9}", "Anon types:", self.anon_cnt)?;
+        if let Some((id, name, sz)) = &self.largest_struct {
+            write!(
+                f,
+                "\n{:16} {:9} bytes ('{}', id {})",
+                "Largest struct:", sz, name, id
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Btf<'a> {
+    pub fn stats(&self) -> BtfStats {
+        let mut kinds: HashMap<BtfKind, KindStats> = HashMap::new();
+        let mut stats = BtfStats {
+            kinds: Vec::new(),
+            type_cnt: 0,
+            type_bytes: 0,
+            str_bytes: self.strs().len(),
+            named_cnt: 0,
+            anon_cnt: 0,
+            largest_struct: None,
+        };
+        for (id, t) in self.iter().skip(self.base_type_cnt().max(1) as usize) {
+            let bytes = Btf::type_size(t);
+            let k = kinds.entry(t.kind()).or_insert(KindStats {
+                kind: t.kind(),
+                cnt: 0,
+                bytes: 0,
+            });
+            k.cnt += 1;
+            k.bytes += bytes;
+            stats.type_cnt += 1;
+            stats.type_bytes += bytes;
+            if t.name().is_empty() {
+                stats.anon_cnt += 1;
+            } else {
+                stats.named_cnt += 1;
+            }
+            if let BtfType::Struct(s) = t {
+                if stats.largest_struct.as_ref().is_none_or(|l| s.sz > l.2) {
+                    stats.largest_struct = Some((id, s.name.to_owned(), s.sz));
+                }
+            }
+        }
+        stats.kinds = kinds.into_values().collect();
+        stats
+            .kinds
+            .sort_by(|a, b| b.bytes.cmp(&a.bytes).then(b.cnt.cmp(&a.cnt)));
+        stats
+    }
+    /// Bytes each own type's strings (its name, as well as names of its members, params, and
+    /// enumerators, each with NUL terminator) would take in string section before strings are
+    /// deduplicated, for finding types inflating BTF the most. Types without any names are
+    /// omitted; the rest are ordered from the most bytes to the least, then by ID.
+    pub fn string_usage(&self) -> Vec<(u32, usize)> {
+        let str_bytes = |s: &str| if s.is_empty() { 0 } else { s.len() + 1 };
+        let mut usage: Vec<(u32, usize)> = self
+            .iter()
+            .skip(self.base_type_cnt().max(1) as usize)
+            .map(|(id, t)| {
+                let inner: usize = match t {
+                    BtfType::Struct(t) | BtfType::Union(t) => {
+                        t.members.iter().map(|m| str_bytes(m.name)).sum()
+                    }
+                    BtfType::Enum(t) | BtfType::Enum64(t) => {
+                        t.values.iter().map(|v| str_bytes(v.name)).sum()
+                    }
+                    BtfType::FuncProto(t) => t.params.iter().map(|p| str_bytes(p.name)).sum(),
+                    _ => 0,
+                };
+                (id, str_bytes(t.name()) + inner)
+            })
+            .filter(|&(_, bytes)| bytes > 0)
+            .collect();
+        usage.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        usage
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use crate::{BtfError, BtfResult};
+
+/// Read-only view of BTF string section: a sequence of NUL-terminated strings, referenced by
+/// their byte offset within the section.
+#[derive(Debug, Copy, Clone)]
+pub struct StringTable<'a> {
+    data: &'a [u8],
+    /// string section of base BTF, for split BTF; offsets below its size refer to base strings,
+    /// while own strings start right after it
+    base: &'a [u8],
+}
+
+impl<'a> StringTable<'a> {
+    pub fn new(data: &'a [u8]) -> StringTable<'a> {
+        StringTable { data, base: &[] }
+    }
+
+    /// String section of split BTF, continuing string section of its base BTF.
+    pub fn new_split(base: &'a [u8], data: &'a [u8]) -> StringTable<'a> {
+        StringTable { data, base }
+    }
+
+    /// Offset of the first own string, i.e., size of base string section, if any
+    pub fn start_off(&self) -> u32 {
+        self.base.len() as u32
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn get(&self, off: u32) -> BtfResult<&'a str> {
+        if off < self.start_off() {
+            return StringTable::new(self.base).get(off);
+        }
+        let s = match self.data.get((off - self.start_off()) as usize..) {
+            Some(s) if !s.is_empty() => s,
+            _ => return Err(Box::new(BtfError::BadStringOffset(off))),
+        };
+        let len = match s.iter().position(|&c| c == 0) {
+            Some(len) => len,
+            None => return Err(Box::new(BtfError::UnterminatedString(off))),
+        };
+        match std::str::from_utf8(&s[..len]) {
+            Ok(s) => Ok(s),
+            Err(_) => Err(Box::new(BtfError::InvalidString(off))),
+        }
+    }
+
+    /// Offset of a string borrowed from this table (e.g., a type or member name returned by
+    /// `get()`), or `None` if it doesn't point into table's data, e.g., if it was allocated
+    /// separately. Empty strings not borrowed from the table map to offset 0.
+    pub fn offset_of(&self, s: &str) -> Option<u32> {
+        let addr = s.as_ptr() as usize;
+        let within = |data: &[u8]| {
+            let start = data.as_ptr() as usize;
+            if addr >= start && addr + s.len() < start + data.len() {
+                Some((addr - start) as u32)
+            } else {
+                None
+            }
+        };
+        within(self.data)
+            .map(|off| self.start_off() + off)
+            .or_else(|| within(self.base))
+            .or(if s.is_empty() { Some(0) } else { None })
+    }
+
+    /// Iterates over all own strings in the section (base ones excluded), yielding each string
+    /// along with its offset.
+    pub fn iter(&self) -> StringTableIter<'a> {
+        StringTableIter {
+            data: self.data,
+            off: 0,
+            start_off: self.start_off(),
+        }
+    }
+}
+
+pub struct StringTableIter<'a> {
+    data: &'a [u8],
+    off: usize,
+    start_off: u32,
+}
+
+impl<'a> Iterator for StringTableIter<'a> {
+    type Item = (u32, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.off < self.data.len() {
+            let off = self.off;
+            let s = &self.data[off..];
+            let len = s.iter().position(|&c| c == 0).unwrap_or(s.len());
+            self.off += len + 1;
+            // skip invalid UTF-8 strings, they can't be referenced successfully anyways
+            if let Ok(s) = std::str::from_utf8(&s[..len]) {
+                return Some((self.start_off + off as u32, s));
+            }
+        }
+        None
+    }
+}
+
+/// Builder of BTF string section. Each distinct string is stored only once, empty string is
+/// always at offset 0.
+#[derive(Debug)]
+pub struct StringTableBuilder {
+    data: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl Default for StringTableBuilder {
+    fn default() -> Self {
+        StringTableBuilder::new()
+    }
+}
+
+impl StringTableBuilder {
+    pub fn new() -> StringTableBuilder {
+        let mut offsets = HashMap::new();
+        offsets.insert(String::new(), 0);
+        StringTableBuilder {
+            data: vec![0],
+            offsets,
+        }
+    }
+
+    /// Returns offset of a given string, appending it to the string section, if it's not there
+    /// yet.
+    pub fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&off) = self.offsets.get(s) {
+            return off;
+        }
+        let off = self.data.len() as u32;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        self.offsets.insert(s.to_owned(), off);
+        off
+    }
+
+    pub fn as_table(&self) -> StringTable<'_> {
+        StringTable::new(&self.data)
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_dedups_strings() {
+        let mut b = StringTableBuilder::new();
+        assert_eq!(b.intern(""), 0);
+        let foo = b.intern("foo");
+        let bar = b.intern("bar");
+        assert_eq!((foo, bar), (1, 5));
+        assert_eq!(b.intern("foo"), foo);
+        assert_eq!(b.intern("bar"), bar);
+        assert_eq!(b.as_table().get(bar).unwrap(), "bar");
+        assert_eq!(b.into_bytes(), b"\0foo\0bar\0");
+    }
+
+    #[test]
+    fn read_strings() {
+        let data = b"\0int\0long\0";
+        let strs = StringTable::new(data);
+        assert_eq!(strs.get(0).unwrap(), "");
+        assert_eq!(strs.get(1).unwrap(), "int");
+        assert_eq!(strs.get(5).unwrap(), "long");
+        // offsets into the middle of a string are valid in BTF, yielding its suffix
+        assert_eq!(strs.get(7).unwrap(), "ng");
+        assert!(strs.get(10).is_err());
+        assert_eq!(
+            strs.iter().collect::<Vec<_>>(),
+            [(0, ""), (1, "int"), (5, "long")]
+        );
+
+        let int = strs.get(1).unwrap();
+        assert_eq!(strs.offset_of(int), Some(1));
+        assert_eq!(strs.offset_of("int"), None);
+
+        // split table continues base one, own strings start after base strings
+        let split = StringTable::new_split(data, b"\0own\0");
+        assert_eq!(split.get(1).unwrap(), "int");
+        assert_eq!(split.get(11).unwrap(), "own");
+        assert_eq!(split.iter().collect::<Vec<_>>(), [(10, ""), (11, "own")]);
+    }
+}
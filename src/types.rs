@@ -1,18 +1,30 @@
 use std::cmp::{max, min};
-use std::ffi::{c_char, CStr};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::error::Error;
 use std::fmt;
+use std::io::Read;
 use std::mem::size_of;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use flate2::read::GzDecoder;
 
 use object::{Object, ObjectSection};
 use scroll::Pread;
 use scroll_derive::{IOread, IOwrite, Pread as DerivePread, Pwrite, SizeWith};
 
+use crate::strings::StringTable;
 use crate::{btf_error, BtfError, BtfResult};
 
 pub const BTF_ELF_SEC: &str = ".BTF";
 pub const BTF_EXT_ELF_SEC: &str = ".BTF.ext";
+/// Raw BTF of the running kernel, exposed by kernels built with CONFIG_DEBUG_INFO_BTF=y
+pub const VMLINUX_BTF_PATH: &str = "/sys/kernel/btf/vmlinux";
 
 pub const BTF_MAGIC: u16 = 0xeB9F;
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
 pub const BTF_VERSION: u8 = 1;
 
 pub const BTF_KIND_UNKN: u32 = 0;
@@ -34,7 +46,8 @@ pub const BTF_KIND_DATASEC: u32 = 15;
 pub const BTF_KIND_FLOAT: u32 = 16;
 pub const BTF_KIND_DECL_TAG: u32 = 17;
 pub const BTF_KIND_TYPE_TAG: u32 = 18;
-pub const BTF_KIND_MAX: u32 = 18;
+pub const BTF_KIND_ENUM64: u32 = 19;
+pub const BTF_KIND_MAX: u32 = 19;
 pub const NR_BTF_KINDS: u32 = BTF_KIND_MAX + 1;
 
 pub const BTF_INT_SIGNED: u32 = 0b001;
@@ -77,6 +90,14 @@ pub struct btf_enum {
     pub val: i32,
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone, DerivePread, Pwrite, SizeWith)]
+pub struct btf_enum64 {
+    pub name_off: u32,
+    pub val_lo32: u32,
+    pub val_hi32: u32,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, DerivePread, Pwrite, IOread, IOwrite, SizeWith)]
 pub struct btf_array {
@@ -202,7 +223,7 @@ fn disp_name(s: &str) -> &str {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BtfIntEncoding {
     None,
     Signed,
@@ -221,14 +242,25 @@ impl fmt::Display for BtfIntEncoding {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfInt<'a> {
     pub name: &'a str,
+    /// size in bytes of int's storage
+    pub sz: u32,
     pub bits: u32,
+    /// bit offset of value within int's storage, value occupies `bits` bits starting from it
     pub offset: u32,
     pub encoding: BtfIntEncoding,
 }
 
+impl<'a> BtfInt<'a> {
+    /// Whether int's value occupies its whole storage, i.e., it can be used as is as a type of
+    /// non-bitfield member
+    pub fn is_full_width(&self) -> bool {
+        self.offset == 0 && self.bits == self.sz * 8
+    }
+}
+
 impl<'a> fmt::Display for BtfInt<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -247,7 +279,7 @@ impl<'a> fmt::Display for BtfInt<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfPtr {
     pub type_id: u32,
 }
@@ -258,7 +290,7 @@ impl fmt::Display for BtfPtr {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfArray {
     pub nelems: u32,
     pub idx_type_id: u32,
@@ -275,7 +307,7 @@ impl fmt::Display for BtfArray {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfMember<'a> {
     pub name: &'a str,
     pub type_id: u32,
@@ -293,7 +325,7 @@ impl<'a> fmt::Display for BtfMember<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfComposite<'a> {
     pub is_struct: bool,
     pub name: &'a str,
@@ -318,10 +350,12 @@ impl<'a> fmt::Display for BtfComposite<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfEnumValue<'a> {
     pub name: &'a str,
-    pub value: i32,
+    /// raw value bits, sign-extended; values of unsigned enums above `i32::MAX` (`i64::MAX` for
+    /// 64-bit ones) come out negative, use `BtfEnum::value_of()` to get the actual value
+    pub value: i64,
 }
 
 impl<'a> fmt::Display for BtfEnumValue<'a> {
@@ -330,31 +364,60 @@ impl<'a> fmt::Display for BtfEnumValue<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfEnum<'a> {
     pub name: &'a str,
     pub sz: u32,
+    /// BTF_KIND_ENUM64, as opposed to BTF_KIND_ENUM with 32-bit values
+    pub is_64: bool,
+    /// whether values are signed, as marked by kflag
+    pub is_signed: bool,
     pub values: Vec<BtfEnumValue<'a>>,
 }
 
+impl<'a> BtfEnum<'a> {
+    /// Value of enumerator, taking enum's signedness into account; values of unsigned enums are
+    /// zero-extended from enum's size (or 32 bits for non-64-bit enums, whichever is smaller)
+    pub fn value_of(&self, v: &BtfEnumValue) -> i128 {
+        if self.is_signed {
+            return v.value as i128;
+        }
+        let max_bits = if self.is_64 { 64 } else { 32 };
+        let bits = match self.sz {
+            1..=7 => (self.sz * 8).min(max_bits),
+            _ => max_bits,
+        };
+        (v.value as u64 & (u64::MAX >> (64 - bits))) as i128
+    }
+}
+
 impl<'a> fmt::Display for BtfEnum<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
             "<{}> '{}' sz:{} n:{}",
-            "ENUM",
+            if self.is_64 { "ENUM64" } else { "ENUM" },
             disp_name(self.name),
             self.sz,
             self.values.len()
         )?;
-        for i in 0..self.values.len() {
-            write!(f, "\n\t#{:02} {}", i, self.values[i])?;
+        if self.is_signed {
+            write!(f, " signed")?;
+        }
+        for (i, v) in self.values.iter().enumerate() {
+            write!(
+                f,
+                "\n\t#{:02} {} = {}",
+                i,
+                disp_name(v.name),
+                self.value_of(v)
+            )?;
         }
         Ok(())
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BtfFwdKind {
     Struct,
     Union,
@@ -369,7 +432,7 @@ impl fmt::Display for BtfFwdKind {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfFwd<'a> {
     pub name: &'a str,
     pub kind: BtfFwdKind,
@@ -387,7 +450,7 @@ impl<'a> fmt::Display for BtfFwd<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfTypedef<'a> {
     pub name: &'a str,
     pub type_id: u32,
@@ -405,7 +468,7 @@ impl<'a> fmt::Display for BtfTypedef<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfVolatile {
     pub type_id: u32,
 }
@@ -416,7 +479,7 @@ impl fmt::Display for BtfVolatile {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfConst {
     pub type_id: u32,
 }
@@ -427,7 +490,7 @@ impl fmt::Display for BtfConst {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfRestrict {
     pub type_id: u32,
 }
@@ -438,7 +501,7 @@ impl fmt::Display for BtfRestrict {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BtfFuncKind {
     Unknown,
     Static,
@@ -457,7 +520,7 @@ impl fmt::Display for BtfFuncKind {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfFunc<'a> {
     pub name: &'a str,
     pub proto_type_id: u32,
@@ -477,7 +540,7 @@ impl<'a> fmt::Display for BtfFunc<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfFuncParam<'a> {
     pub name: &'a str,
     pub type_id: u32,
@@ -489,12 +552,34 @@ impl<'a> fmt::Display for BtfFuncParam<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfFuncProto<'a> {
     pub res_type_id: u32,
     pub params: Vec<BtfFuncParam<'a>>,
 }
 
+impl<'a> BtfFuncProto<'a> {
+    /// Whether func proto has a single void param, which is how clang encodes explicit no args,
+    /// i.e., `(void)`, while GCC encodes it with no params at all
+    pub fn is_void_args(&self) -> bool {
+        self.params.len() == 1 && self.params[0].type_id == 0
+    }
+
+    /// Whether func proto takes variable number of args, marked by a trailing void param
+    pub fn is_variadic(&self) -> bool {
+        self.params.len() > 1 && self.params[self.params.len() - 1].type_id == 0
+    }
+
+    /// Actual params, i.e., excluding void param marking either no args or varargs
+    pub fn args(&self) -> &[BtfFuncParam<'a>] {
+        if self.is_void_args() || self.is_variadic() {
+            &self.params[..self.params.len() - 1]
+        } else {
+            &self.params
+        }
+    }
+}
+
 impl<'a> fmt::Display for BtfFuncProto<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -511,7 +596,7 @@ impl<'a> fmt::Display for BtfFuncProto<'a> {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BtfVarKind {
     Static,
     GlobalAlloc,
@@ -528,7 +613,7 @@ impl fmt::Display for BtfVarKind {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfVar<'a> {
     pub name: &'a str,
     pub type_id: u32,
@@ -548,7 +633,7 @@ impl<'a> fmt::Display for BtfVar<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfDatasecVar {
     pub type_id: u32,
     pub offset: u32,
@@ -565,7 +650,7 @@ impl fmt::Display for BtfDatasecVar {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfDatasec<'a> {
     pub name: &'a str,
     pub sz: u32,
@@ -589,7 +674,7 @@ impl<'a> fmt::Display for BtfDatasec<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfFloat<'a> {
     pub name: &'a str,
     pub sz: u32,
@@ -602,11 +687,13 @@ impl<'a> fmt::Display for BtfFloat<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfDeclTag<'a> {
     pub name: &'a str,
     pub type_id: u32,
-    pub comp_idx: u32,
+    /// index of struct/union member or func param the tag applies to, or -1 if it applies to
+    /// the type (or func, var) itself
+    pub comp_idx: i32,
 }
 
 impl<'a> fmt::Display for BtfDeclTag<'a> {
@@ -622,7 +709,7 @@ impl<'a> fmt::Display for BtfDeclTag<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BtfTypeTag<'a> {
     pub name: &'a str,
     pub type_id: u32,
@@ -640,7 +727,7 @@ impl<'a> fmt::Display for BtfTypeTag<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum BtfType<'a> {
     Void,
     Int(BtfInt<'a>),
@@ -649,6 +736,7 @@ pub enum BtfType<'a> {
     Struct(BtfComposite<'a>),
     Union(BtfComposite<'a>),
     Enum(BtfEnum<'a>),
+    Enum64(BtfEnum<'a>),
     Fwd(BtfFwd<'a>),
     Typedef(BtfTypedef<'a>),
     Volatile(BtfVolatile),
@@ -673,6 +761,7 @@ impl<'a> fmt::Display for BtfType<'a> {
             BtfType::Struct(t) => t.fmt(f),
             BtfType::Union(t) => t.fmt(f),
             BtfType::Enum(t) => t.fmt(f),
+            BtfType::Enum64(t) => t.fmt(f),
             BtfType::Fwd(t) => t.fmt(f),
             BtfType::Typedef(t) => t.fmt(f),
             BtfType::Volatile(t) => t.fmt(f),
@@ -699,6 +788,7 @@ impl<'a> BtfType<'a> {
             BtfType::Struct(_) => BtfKind::Struct,
             BtfType::Union(_) => BtfKind::Union,
             BtfType::Enum(_) => BtfKind::Enum,
+            BtfType::Enum64(_) => BtfKind::Enum64,
             BtfType::Fwd(_) => BtfKind::Fwd,
             BtfType::Typedef(_) => BtfKind::Typedef,
             BtfType::Volatile(_) => BtfKind::Volatile,
@@ -714,27 +804,130 @@ impl<'a> BtfType<'a> {
         }
     }
 
-    pub fn name(&self) -> &str {
+    pub fn category(&self) -> TypeCategory {
+        match self {
+            BtfType::Int(_) | BtfType::Enum(_) | BtfType::Enum64(_) => TypeCategory::Integer,
+            BtfType::Float(_) => TypeCategory::Float,
+            BtfType::Ptr(_) => TypeCategory::Pointer,
+            BtfType::Struct(_) | BtfType::Union(_) | BtfType::Array(_) | BtfType::Fwd(_) => {
+                TypeCategory::Aggregate
+            }
+            BtfType::Func(_) | BtfType::FuncProto(_) => TypeCategory::Function,
+            BtfType::Typedef(_) => TypeCategory::Alias,
+            BtfType::Volatile(_)
+            | BtfType::Const(_)
+            | BtfType::Restrict(_)
+            | BtfType::TypeTag(_) => TypeCategory::Qualifier,
+            BtfType::Void | BtfType::Var(_) | BtfType::Datasec(_) | BtfType::DeclTag(_) => {
+                TypeCategory::Special
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'a str {
         match self {
             BtfType::Void => EMPTY,
-            BtfType::Int(t) => &t.name,
+            BtfType::Int(t) => t.name,
             BtfType::Ptr(_) => EMPTY,
             BtfType::Array(_) => EMPTY,
-            BtfType::Struct(t) => &t.name,
-            BtfType::Union(t) => &t.name,
-            BtfType::Enum(t) => &t.name,
-            BtfType::Fwd(t) => &t.name,
-            BtfType::Typedef(t) => &t.name,
+            BtfType::Struct(t) => t.name,
+            BtfType::Union(t) => t.name,
+            BtfType::Enum(t) => t.name,
+            BtfType::Enum64(t) => t.name,
+            BtfType::Fwd(t) => t.name,
+            BtfType::Typedef(t) => t.name,
             BtfType::Volatile(_) => EMPTY,
             BtfType::Const(_) => EMPTY,
             BtfType::Restrict(_) => EMPTY,
-            BtfType::Func(t) => &t.name,
+            BtfType::Func(t) => t.name,
             BtfType::FuncProto(_) => EMPTY,
-            BtfType::Var(t) => &t.name,
-            BtfType::Datasec(t) => &t.name,
-            BtfType::Float(t) => &t.name,
-            BtfType::DeclTag(t) => &t.name,
-            BtfType::TypeTag(t) => &t.name,
+            BtfType::Var(t) => t.name,
+            BtfType::Datasec(t) => t.name,
+            BtfType::Float(t) => t.name,
+            BtfType::DeclTag(t) => t.name,
+            BtfType::TypeTag(t) => t.name,
+        }
+    }
+
+    /// Calls `f` for every type ID this type references.
+    pub fn visit_type_ids<F: FnMut(u32)>(&self, mut f: F) {
+        match self {
+            BtfType::Void
+            | BtfType::Int(_)
+            | BtfType::Enum(_)
+            | BtfType::Enum64(_)
+            | BtfType::Fwd(_)
+            | BtfType::Float(_) => {}
+            BtfType::Ptr(t) => f(t.type_id),
+            BtfType::Array(t) => {
+                f(t.idx_type_id);
+                f(t.val_type_id);
+            }
+            BtfType::Struct(t) | BtfType::Union(t) => {
+                for m in &t.members {
+                    f(m.type_id);
+                }
+            }
+            BtfType::Typedef(t) => f(t.type_id),
+            BtfType::Volatile(t) => f(t.type_id),
+            BtfType::Const(t) => f(t.type_id),
+            BtfType::Restrict(t) => f(t.type_id),
+            BtfType::Func(t) => f(t.proto_type_id),
+            BtfType::FuncProto(t) => {
+                f(t.res_type_id);
+                for p in &t.params {
+                    f(p.type_id);
+                }
+            }
+            BtfType::Var(t) => f(t.type_id),
+            BtfType::Datasec(t) => {
+                for v in &t.vars {
+                    f(v.type_id);
+                }
+            }
+            BtfType::DeclTag(t) => f(t.type_id),
+            BtfType::TypeTag(t) => f(t.type_id),
+        }
+    }
+
+    /// Replaces every type ID this type references with the result of `f` applied to it.
+    pub fn remap_type_ids<F: FnMut(u32) -> u32>(&mut self, mut f: F) {
+        match self {
+            BtfType::Void
+            | BtfType::Int(_)
+            | BtfType::Enum(_)
+            | BtfType::Enum64(_)
+            | BtfType::Fwd(_)
+            | BtfType::Float(_) => {}
+            BtfType::Ptr(t) => t.type_id = f(t.type_id),
+            BtfType::Array(t) => {
+                t.idx_type_id = f(t.idx_type_id);
+                t.val_type_id = f(t.val_type_id);
+            }
+            BtfType::Struct(t) | BtfType::Union(t) => {
+                for m in &mut t.members {
+                    m.type_id = f(m.type_id);
+                }
+            }
+            BtfType::Typedef(t) => t.type_id = f(t.type_id),
+            BtfType::Volatile(t) => t.type_id = f(t.type_id),
+            BtfType::Const(t) => t.type_id = f(t.type_id),
+            BtfType::Restrict(t) => t.type_id = f(t.type_id),
+            BtfType::Func(t) => t.proto_type_id = f(t.proto_type_id),
+            BtfType::FuncProto(t) => {
+                t.res_type_id = f(t.res_type_id);
+                for p in &mut t.params {
+                    p.type_id = f(p.type_id);
+                }
+            }
+            BtfType::Var(t) => t.type_id = f(t.type_id),
+            BtfType::Datasec(t) => {
+                for v in &mut t.vars {
+                    v.type_id = f(v.type_id);
+                }
+            }
+            BtfType::DeclTag(t) => t.type_id = f(t.type_id),
+            BtfType::TypeTag(t) => t.type_id = f(t.type_id),
         }
     }
 }
@@ -760,6 +953,92 @@ pub enum BtfKind {
     Float,
     DeclTag,
     TypeTag,
+    Enum64,
+}
+
+/// Coarse classification of BTF kinds, grouping them by the role they play in C type system.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum TypeCategory {
+    /// ints and enums
+    Integer,
+    Float,
+    Pointer,
+    /// structs, unions, arrays, and fwd declarations of structs/unions
+    Aggregate,
+    /// funcs and func protos
+    Function,
+    /// typedefs
+    Alias,
+    /// const, volatile, restrict, and type tags
+    Qualifier,
+    /// void, vars, datasecs, and decl tags
+    Special,
+}
+
+impl fmt::Display for TypeCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeCategory::Integer => write!(f, "integer"),
+            TypeCategory::Float => write!(f, "float"),
+            TypeCategory::Pointer => write!(f, "pointer"),
+            TypeCategory::Aggregate => write!(f, "aggregate"),
+            TypeCategory::Function => write!(f, "function"),
+            TypeCategory::Alias => write!(f, "alias"),
+            TypeCategory::Qualifier => write!(f, "qualifier"),
+            TypeCategory::Special => write!(f, "special"),
+        }
+    }
+}
+
+impl BtfKind {
+    pub const ALL: [BtfKind; NR_BTF_KINDS as usize] = [
+        BtfKind::Void,
+        BtfKind::Int,
+        BtfKind::Ptr,
+        BtfKind::Array,
+        BtfKind::Struct,
+        BtfKind::Union,
+        BtfKind::Enum,
+        BtfKind::Fwd,
+        BtfKind::Typedef,
+        BtfKind::Volatile,
+        BtfKind::Const,
+        BtfKind::Restrict,
+        BtfKind::Func,
+        BtfKind::FuncProto,
+        BtfKind::Var,
+        BtfKind::Datasec,
+        BtfKind::Float,
+        BtfKind::DeclTag,
+        BtfKind::TypeTag,
+        BtfKind::Enum64,
+    ];
+
+    /// Lowercase name of kind, as accepted by `from_str()`, e.g., `struct` or `func_proto`
+    pub fn keyword(self) -> &'static str {
+        match self {
+            BtfKind::Void => "void",
+            BtfKind::Int => "int",
+            BtfKind::Ptr => "ptr",
+            BtfKind::Array => "array",
+            BtfKind::Struct => "struct",
+            BtfKind::Union => "union",
+            BtfKind::Enum => "enum",
+            BtfKind::Fwd => "fwd",
+            BtfKind::Typedef => "typedef",
+            BtfKind::Volatile => "volatile",
+            BtfKind::Const => "const",
+            BtfKind::Restrict => "restrict",
+            BtfKind::Func => "func",
+            BtfKind::FuncProto => "func_proto",
+            BtfKind::Var => "var",
+            BtfKind::Datasec => "datasec",
+            BtfKind::Float => "float",
+            BtfKind::DeclTag => "decl_tag",
+            BtfKind::TypeTag => "type_tag",
+            BtfKind::Enum64 => "enum64",
+        }
+    }
 }
 
 impl std::str::FromStr for BtfKind {
@@ -783,9 +1062,10 @@ impl std::str::FromStr for BtfKind {
             "func" | "fn" => Ok(BtfKind::Func),
             "var" | "v" => Ok(BtfKind::Var),
             "datasec" => Ok(BtfKind::Datasec),
-            "float" => Ok(BtfKind::Float),
-            "decl_tag" => Ok(BtfKind::DeclTag),
-            "type_tag" => Ok(BtfKind::TypeTag),
+            "float" | "f" => Ok(BtfKind::Float),
+            "decl_tag" | "decltag" => Ok(BtfKind::DeclTag),
+            "type_tag" | "typetag" => Ok(BtfKind::TypeTag),
+            "enum64" | "e64" => Ok(BtfKind::Enum64),
             _ => Err(BtfError::new_owned(format!(
                 "unrecognized btf kind: '{}'",
                 s
@@ -794,14 +1074,14 @@ impl std::str::FromStr for BtfKind {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BtfExtSection<'a, T> {
     pub name: &'a str,
     pub rec_sz: usize,
     pub recs: Vec<T>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BtfExtFunc {
     pub insn_off: u32,
     pub type_id: u32,
@@ -818,7 +1098,7 @@ impl fmt::Display for BtfExtFunc {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BtfExtLine<'a> {
     pub insn_off: u32,
     pub file_name: &'a str,
@@ -841,7 +1121,7 @@ impl<'a> fmt::Display for BtfExtLine<'a> {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum BtfCoreRelocKind {
     ByteOff = 0,
     ByteSz = 1,
@@ -878,7 +1158,7 @@ impl fmt::Display for BtfCoreRelocKind {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BtfExtCoreReloc<'a> {
     pub insn_off: u32,
     pub type_id: u32,
@@ -900,11 +1180,43 @@ impl<'a> fmt::Display for BtfExtCoreReloc<'a> {
     }
 }
 
+/// Failure to parse one of BTF data blobs during `Btf::load_all_sections()`
+#[derive(Debug)]
+pub struct SectionLoadError {
+    pub section: String,
+    /// offset of failed BTF blob within the section
+    pub offset: usize,
+    pub error: Box<dyn std::error::Error>,
+}
+
+impl fmt::Display for SectionLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at offset {}: {}",
+            self.section, self.offset, self.error
+        )
+    }
+}
+
+/// Parsed BTF types (and .BTF.ext records) along with string section. Strings borrow from data
+/// BTF was loaded from, but nothing else does; see `OwnedBtf` for BTF owning that data.
 #[derive(Debug)]
 pub struct Btf<'a> {
     endian: scroll::Endian,
+    version: u8,
+    flags: u8,
     types: Vec<BtfType<'a>>,
+    strs: StringTable<'a>,
     ptr_sz: u32,
+    /// base BTF, for split BTF; types with IDs below `base_type_cnt` are looked up there
+    base: Option<&'a Btf<'a>>,
+    /// number of types (including void) coming from base BTF, for split BTF
+    base_type_cnt: u32,
+    /// IDs of named types, by kind and name
+    name_index: HashMap<(BtfKind, &'a str), Vec<u32>>,
+    /// alignments of all types, by type ID, computed on the first `get_align_of()` call
+    aligns: OnceLock<Vec<u32>>,
 
     // .BTF.ext stuff
     has_ext: bool,
@@ -913,21 +1225,230 @@ pub struct Btf<'a> {
     core_reloc_secs: Vec<BtfExtSection<'a, BtfExtCoreReloc<'a>>>,
 }
 
+/// .BTF.ext records of BPF object file, by name of ELF section (i.e., BPF program) they describe,
+/// as returned by `Btf::load_ext()`. Type IDs and strings in them refer to the main BTF.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BtfExt<'a> {
+    pub func_infos: BTreeMap<&'a str, Vec<BtfExtFunc>>,
+    pub line_infos: BTreeMap<&'a str, Vec<BtfExtLine<'a>>>,
+    pub core_relos: BTreeMap<&'a str, Vec<BtfExtCoreReloc<'a>>>,
+}
+
+/// .BTF.ext records, as loaded into `Btf`
+#[derive(Default)]
+struct ExtSecs<'a> {
+    func_secs: Vec<BtfExtSection<'a, BtfExtFunc>>,
+    line_secs: Vec<BtfExtSection<'a, BtfExtLine<'a>>>,
+    core_reloc_secs: Vec<BtfExtSection<'a, BtfExtCoreReloc<'a>>>,
+}
+
+fn by_sec_name<'a, T>(secs: Vec<BtfExtSection<'a, T>>) -> BTreeMap<&'a str, Vec<T>> {
+    secs.into_iter().map(|s| (s.name, s.recs)).collect()
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+const _: fn() = assert_send_sync::<Btf<'static>>;
+const _: fn() = assert_send_sync::<OwnedBtf<'static>>;
+
+/// BTF along with data it was loaded from, as returned by loaders reading data themselves (e.g.,
+/// `Btf::from_owned_bytes()`). Data is freed along with BTF, so `OwnedBtf` can be stored,
+/// cached, and shared between threads without keeping anything else alive. For split BTF,
+/// `'b` is the lifetime of base BTF, otherwise it's `'static`.
+pub struct OwnedBtf<'b> {
+    // borrows from `data`, so has to be declared (and thus dropped) before it
+    btf: Btf<'b>,
+    _data: Arc<[u8]>,
+}
+
+impl<'b> OwnedBtf<'b> {
+    /// Loads BTF from `data` with `load`, keeping data alive for as long as BTF is.
+    fn new<F>(data: Vec<u8>, load: F) -> BtfResult<OwnedBtf<'b>>
+    where
+        F: FnOnce(&'b [u8]) -> BtfResult<Btf<'b>>,
+    {
+        let data: Arc<[u8]> = data.into();
+        // SAFETY: data is heap-allocated and immutable, and stays at the same address for as
+        // long as `Arc` (and so `OwnedBtf`) is alive; `btf()` shortens borrowed lifetime to that
+        // of `OwnedBtf`, so nothing borrowed from data can outlive it
+        let bytes: &'b [u8] = unsafe { &*(&*data as *const [u8]) };
+        let btf = load(bytes)?;
+        Ok(OwnedBtf { btf, _data: data })
+    }
+
+    pub fn btf(&self) -> &Btf<'_> {
+        &self.btf
+    }
+}
+
+impl<'b> fmt::Debug for OwnedBtf<'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.btf.fmt(f)
+    }
+}
+
 impl<'a> Btf<'a> {
     pub fn ptr_sz(&self) -> u32 {
         self.ptr_sz
     }
 
-    pub fn types(&self) -> &[BtfType] {
+    /// Overrides pointer size, which is otherwise derived from ELF class (or passed in, for raw
+    /// BTF), e.g., to reinterpret 64-bit BTF for a 32-bit target. This changes sizes of pointers
+    /// and alignment of all types (which is capped at pointer size), and with it packed/aligned
+    /// struct detection and padding emitted by C dumper. Sizes and offsets recorded in BTF are
+    /// kept as is, so struct and `long` sizes don't change.
+    pub fn set_ptr_sz(&mut self, sz: u32) {
+        self.ptr_sz = sz;
+        self.aligns = OnceLock::new();
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn endian(&self) -> scroll::Endian {
+        self.endian
+    }
+
+    /// Types of this BTF, indexed by type ID. For split BTF, these are only its own types,
+    /// starting with ID `base_type_cnt()`; see `iter()` to go over all types.
+    pub fn types(&self) -> &[BtfType<'a>] {
         &self.types
     }
 
-    pub fn type_by_id(&self, type_id: u32) -> &BtfType {
-        &self.types[type_id as usize]
+    pub fn type_by_id(&self, type_id: u32) -> &BtfType<'a> {
+        match self.base {
+            Some(base) if type_id < self.base_type_cnt => base.type_by_id(type_id),
+            _ => &self.types[(type_id - self.base_type_cnt) as usize],
+        }
+    }
+
+    /// Same as `type_by_id()`, but returns `None` instead of panicking on out-of-range ID.
+    pub fn type_by_id_opt(&self, type_id: u32) -> Option<&BtfType<'a>> {
+        match self.base {
+            Some(base) if type_id < self.base_type_cnt => base.type_by_id_opt(type_id),
+            _ => self.types.get((type_id - self.base_type_cnt) as usize),
+        }
+    }
+
+    /// Human-readable label of a type for diagnostics: its name prefixed with C keyword for
+    /// structs, unions, enums (including forward declarations), and typedefs (e.g., `struct
+    /// task_struct` or `typedef u32`), or with kind for other named types (e.g., `func
+    /// do_exit`), except for ints and floats, which are labeled just by name. Anonymous types are
+    /// labeled like `<anon struct>`.
+    pub fn full_name(&self, id: u32) -> String {
+        let t = match self.type_by_id_opt(id) {
+            Some(t) => t,
+            None => return format!("<invalid type [{}]>", id),
+        };
+        let kw = match t {
+            BtfType::Void => return "void".to_owned(),
+            BtfType::Int(_) | BtfType::Float(_) => return t.name().to_owned(),
+            BtfType::Enum64(_) => "enum",
+            BtfType::Fwd(fwd) => match fwd.kind {
+                BtfFwdKind::Struct => "struct",
+                BtfFwdKind::Union => "union",
+            },
+            _ => t.kind().keyword(),
+        };
+        if t.name().is_empty() {
+            format!("<anon {}>", kw)
+        } else {
+            format!("{} {}", kw, t.name())
+        }
+    }
+
+    /// Same as type's `Display` output, but prefixed with type's own ID and with each referenced
+    /// type ID followed by full name of that type, e.g., `[7] <PTR> --> [42] (struct
+    /// task_struct)`.
+    pub fn format_type(&self, id: u32) -> String {
+        let t = match self.type_by_id_opt(id) {
+            Some(t) => t,
+            None => return format!("<invalid type [{}]>", id),
+        };
+        // all bracketed numbers in type's Display output are IDs of referenced types
+        let s = t.to_string();
+        let mut res = format!("[{}] ", id);
+        let mut rest = s.as_str();
+        while let Some(start) = rest.find('[') {
+            let end = match rest[start..].find(']') {
+                Some(end) => start + end,
+                None => break,
+            };
+            res.push_str(&rest[..=end]);
+            if let Ok(ref_id) = rest[start + 1..end].parse::<u32>() {
+                res.push_str(&format!(" ({})", self.full_name(ref_id)));
+            }
+            rest = &rest[end + 1..];
+        }
+        res.push_str(rest);
+        res
+    }
+
+    /// ID of the first type with given name and, optionally, kind.
+    pub fn id_by_name(&self, kind: Option<BtfKind>, name: &str) -> Option<u32> {
+        match kind {
+            Some(kind) => self
+                .base
+                .and_then(|base| base.id_by_name(Some(kind), name))
+                .or_else(|| {
+                    self.name_index
+                        .get(&(kind, name))
+                        .and_then(|ids| ids.first().copied())
+                }),
+            None => self.all_by_name(name).first().copied(),
+        }
+    }
+
+    /// IDs of all types with given name, of any kind, in ascending order.
+    pub fn all_by_name(&self, name: &str) -> Vec<u32> {
+        let mut ids: Vec<u32> = BtfKind::ALL
+            .iter()
+            .filter_map(|&kind| self.name_index.get(&(kind, name)))
+            .flatten()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        match self.base {
+            // base types precede own ones
+            Some(base) => [base.all_by_name(name), ids].concat(),
+            None => ids,
+        }
+    }
+
+    /// Iterates over all types (including void) along with their IDs.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &BtfType<'a>)> + '_ {
+        (0..self.type_cnt()).map(move |id| (id, self.type_by_id(id)))
+    }
+
+    /// Iterates over all types of given kind along with their IDs.
+    pub fn iter_kind(&self, kind: BtfKind) -> impl Iterator<Item = (u32, &BtfType<'a>)> + '_ {
+        self.iter().filter(move |(_, t)| t.kind() == kind)
+    }
+
+    /// Iterates over all funcs along with their IDs and prototypes. Funcs that don't reference
+    /// a func proto (which is invalid BTF) are skipped.
+    pub fn functions(&self) -> impl Iterator<Item = (u32, &BtfFunc<'a>, &BtfFuncProto<'a>)> + '_ {
+        self.iter().filter_map(move |(id, t)| match t {
+            BtfType::Func(f) => match self.type_by_id_opt(f.proto_type_id) {
+                Some(BtfType::FuncProto(proto)) => Some((id, f, proto)),
+                _ => None,
+            },
+            _ => None,
+        })
     }
 
     pub fn type_cnt(&self) -> u32 {
-        self.types.len() as u32
+        self.base_type_cnt + self.types.len() as u32
+    }
+
+    /// Number of leading types (including void) that come from base BTF, if this is split BTF
+    /// loaded with `load_split()`, or 0 otherwise. Own types start with this ID.
+    pub fn base_type_cnt(&self) -> u32 {
+        self.base_type_cnt
     }
 
     pub fn has_ext(&self) -> bool {
@@ -946,66 +1467,249 @@ impl<'a> Btf<'a> {
         &self.core_reloc_secs
     }
 
-    pub fn get_size_of(&self, type_id: u32) -> u32 {
-        match self.type_by_id(type_id) {
-            BtfType::Void => 0,
-            BtfType::Int(t) => (t.bits + 7) / 8,
-            BtfType::Volatile(t) => self.get_size_of(t.type_id),
-            BtfType::Const(t) => self.get_size_of(t.type_id),
-            BtfType::Restrict(t) => self.get_size_of(t.type_id),
-            BtfType::Ptr(_) => self.ptr_sz,
-            BtfType::Array(t) => t.nelems * self.get_size_of(t.val_type_id),
-            BtfType::FuncProto(_) => 0,
-            BtfType::Struct(t) => t.sz,
-            BtfType::Union(t) => t.sz,
-            BtfType::Enum(t) => t.sz,
-            BtfType::Fwd(_) => 0,
-            BtfType::Typedef(t) => self.get_size_of(t.type_id),
-            BtfType::Func(_) => 0,
-            BtfType::Var(_) => 0,
-            BtfType::Datasec(t) => t.sz,
-            BtfType::Float(t) => t.sz,
-            BtfType::DeclTag(t) => self.get_size_of(t.type_id),
-            BtfType::TypeTag(t) => self.get_size_of(t.type_id),
+    pub fn strs(&self) -> &StringTable<'a> {
+        &self.strs
+    }
+
+    /// Offset of type's name within string section, as it was encoded in loaded BTF, for tools
+    /// rewriting strings in place. `None` if type ID is out of bounds or type's name wasn't
+    /// loaded from string section (e.g., type was constructed or renamed programmatically).
+    pub fn name_off(&self, id: u32) -> Option<u32> {
+        self.strs.offset_of(self.type_by_id_opt(id)?.name())
+    }
+
+    /// Same as `name_off()`, but for any string taken from this BTF, e.g., member, param, or
+    /// enum value name.
+    pub fn str_off(&self, s: &str) -> Option<u32> {
+        self.strs.offset_of(s)
+    }
+
+    /// Size of a type in bytes. Sizes of (possibly multi-dimensional) arrays are calculated in
+    /// u64, so only the total size has to fit in u32, otherwise an error is returned instead of
+    /// a wrapped around size. Also fails if type is part of a cycle of arrays, modifiers, and
+    /// typedefs (possible only in malformed BTF).
+    pub fn get_size_of(&self, type_id: u32) -> BtfResult<u32> {
+        let mut id = type_id;
+        let mut nelems: u64 = 1;
+        let mut seen = HashSet::new();
+        loop {
+            if !seen.insert(id) {
+                return Err(Box::new(BtfError::TypeCycle { id }));
+            }
+            let sz = match self.type_by_id(id) {
+                BtfType::Void => 0,
+                BtfType::Int(t) => t.sz,
+                BtfType::Volatile(t) => {
+                    id = t.type_id;
+                    continue;
+                }
+                BtfType::Const(t) => {
+                    id = t.type_id;
+                    continue;
+                }
+                BtfType::Restrict(t) => {
+                    id = t.type_id;
+                    continue;
+                }
+                BtfType::Ptr(_) => self.ptr_sz,
+                BtfType::Array(t) => {
+                    // once saturated, total size overflows u32 anyways, unless some other
+                    // dimension or element size is zero, which makes it zero regardless
+                    nelems = nelems.saturating_mul(t.nelems as u64);
+                    id = t.val_type_id;
+                    continue;
+                }
+                BtfType::FuncProto(_) => 0,
+                BtfType::Struct(t) => t.sz,
+                BtfType::Union(t) => t.sz,
+                BtfType::Enum(t) | BtfType::Enum64(t) => t.sz,
+                BtfType::Fwd(_) => 0,
+                BtfType::Typedef(t) => {
+                    id = t.type_id;
+                    continue;
+                }
+                BtfType::Func(_) => 0,
+                BtfType::Var(_) => 0,
+                BtfType::Datasec(t) => t.sz,
+                BtfType::Float(t) => t.sz,
+                BtfType::DeclTag(t) => {
+                    id = t.type_id;
+                    continue;
+                }
+                BtfType::TypeTag(t) => {
+                    id = t.type_id;
+                    continue;
+                }
+            };
+            let total = if sz == 0 {
+                0
+            } else {
+                nelems.saturating_mul(sz as u64)
+            };
+            return match u32::try_from(total) {
+                Ok(sz) => Ok(sz),
+                Err(_) => btf_error(format!(
+                    "Size of type [{}] overflows u32: {} elements of {} bytes",
+                    type_id, nelems, sz
+                )),
+            };
         }
     }
 
+    /// Alignment of a type in bytes. Structs detected to be packed (see `is_struct_packed()`)
+    /// are 1-byte aligned, which is what types embedding them have to take into account.
+    /// Types without alignment of their own (void, forward declarations, funcs, etc.) are
+    /// reported as 1-byte aligned too, so that alignment is never zero.
     pub fn get_align_of(&self, type_id: u32) -> u32 {
-        match self.type_by_id(type_id) {
-            BtfType::Void => 0,
-            BtfType::Int(t) => min(self.ptr_sz, (t.bits + 7) / 8),
-            BtfType::Volatile(t) => self.get_align_of(t.type_id),
-            BtfType::Const(t) => self.get_align_of(t.type_id),
-            BtfType::Restrict(t) => self.get_align_of(t.type_id),
+        let aligns = self.aligns.get_or_init(|| {
+            let mut aligns = vec![0; self.type_cnt() as usize];
+            for id in 0..self.type_cnt() {
+                self.memo_align_of(id, &mut aligns);
+            }
+            aligns
+        });
+        aligns[type_id as usize]
+    }
+
+    /// Computes alignment of a type, reusing alignments of types already computed in `aligns`
+    /// (where 0 marks not yet computed ones), so that types nested many times over are only
+    /// looked into once.
+    fn memo_align_of(&self, type_id: u32, aligns: &mut [u32]) -> u32 {
+        if aligns[type_id as usize] != 0 {
+            return aligns[type_id as usize];
+        }
+        let align = match self.type_by_id(type_id) {
+            BtfType::Void => 1,
+            BtfType::Int(t) => min(self.ptr_sz, t.bits.div_ceil(8).next_power_of_two()),
+            BtfType::Volatile(t) => self.memo_align_of(t.type_id, aligns),
+            BtfType::Const(t) => self.memo_align_of(t.type_id, aligns),
+            BtfType::Restrict(t) => self.memo_align_of(t.type_id, aligns),
             BtfType::Ptr(_) => self.ptr_sz,
-            BtfType::Array(t) => self.get_align_of(t.val_type_id),
-            BtfType::FuncProto(_) => 0,
+            BtfType::Array(t) => self.memo_align_of(t.val_type_id, aligns),
+            BtfType::FuncProto(_) => 1,
             BtfType::Struct(t) => {
-                let mut align = 1;
-                for m in &t.members {
-                    align = max(align, self.get_align_of(m.type_id));
+                let member_aligns: Vec<u32> = t
+                    .members
+                    .iter()
+                    .map(|m| self.memo_align_of(m.type_id, aligns))
+                    .collect();
+                if self.is_packed_with(t, &member_aligns) {
+                    1
+                } else {
+                    member_aligns.into_iter().fold(1, max)
                 }
-                align
             }
             BtfType::Union(t) => {
                 let mut align = 1;
                 for m in &t.members {
-                    align = max(align, self.get_align_of(m.type_id));
+                    align = max(align, self.memo_align_of(m.type_id, aligns));
                 }
                 align
             }
-            BtfType::Enum(t) => min(self.ptr_sz, t.sz),
-            BtfType::Fwd(_) => 0,
-            BtfType::Typedef(t) => self.get_align_of(t.type_id),
-            BtfType::Func(_) => 0,
-            BtfType::Var(_) => 0,
-            BtfType::Datasec(_) => 0,
+            BtfType::Enum(t) | BtfType::Enum64(t) => min(self.ptr_sz, t.sz),
+            BtfType::Fwd(_) => 1,
+            BtfType::Typedef(t) => self.memo_align_of(t.type_id, aligns),
+            BtfType::Func(_) => 1,
+            BtfType::Var(_) => 1,
+            BtfType::Datasec(_) => 1,
             BtfType::Float(t) => min(self.ptr_sz, t.sz),
-            BtfType::DeclTag(_) => 0,
-            BtfType::TypeTag(t) => self.get_align_of(t.type_id),
+            BtfType::DeclTag(_) => 1,
+            BtfType::TypeTag(t) => self.memo_align_of(t.type_id, aligns),
+        };
+        aligns[type_id as usize] = align;
+        align
+    }
+
+    /// Whether struct has to be declared with `__attribute__((packed))` to get its layout: its
+    /// size is not a multiple of its natural alignment, or some of its non-bitfield members are
+    /// not naturally aligned. Even if original struct was marked as packed, but no misalignment
+    /// is detected, packedness has no effect on it, so it's not considered packed.
+    pub fn is_struct_packed(&self, id: u32) -> bool {
+        match self.type_by_id(id) {
+            BtfType::Struct(t) => self.is_packed_with(t, &self.member_aligns(t)),
+            _ => false,
         }
     }
 
+    /// Alignment struct has to be declared with (`__attribute__((aligned(N)))`) to get its size,
+    /// if its size is larger than the end of its last member rounded up to its natural (or 1, if
+    /// packed) alignment. BTF doesn't record alignment itself, so this is the smallest power of
+    /// two that explains the size; it always divides the original alignment, so struct keeps
+    /// the same size and its offsets in other structs stay valid.
+    pub fn get_struct_over_align(&self, id: u32) -> Option<u32> {
+        let t = match self.type_by_id(id) {
+            BtfType::Struct(t) => t,
+            _ => return None,
+        };
+        let aligns = self.member_aligns(t);
+        let align = if self.is_packed_with(t, &aligns) {
+            1
+        } else {
+            aligns.into_iter().fold(1, max)
+        };
+        let mut end_bits = 0u64;
+        for m in &t.members {
+            let bits = if m.bit_size != 0 {
+                m.bit_size as u64
+            } else if self.is_zero_width_bitfield(m) {
+                0
+            } else {
+                self.get_size_of(m.type_id).ok()? as u64 * 8
+            };
+            end_bits = max(end_bits, m.bit_offset as u64 + bits);
+        }
+        let end = end_bits.div_ceil(8);
+        let sz = t.sz as u64;
+        if end.next_multiple_of(align as u64) >= sz {
+            return None;
+        }
+        let mut over_align = align as u64 * 2;
+        while over_align <= sz {
+            if end.next_multiple_of(over_align) == sz {
+                return Some(over_align as u32);
+            }
+            over_align *= 2;
+        }
+        None
+    }
+
+    fn member_aligns(&self, t: &BtfComposite) -> Vec<u32> {
+        t.members
+            .iter()
+            .map(|m| self.get_align_of(m.type_id))
+            .collect()
+    }
+
+    fn is_packed_with(&self, t: &BtfComposite, aligns: &[u32]) -> bool {
+        // size of a struct has to be a multiple of its alignment
+        if !t.sz.is_multiple_of(aligns.iter().copied().fold(1, max)) {
+            return true;
+        }
+        // all the non-bitfield fields have to be naturally aligned; ints not filling their
+        // storage are bitfields in disguise, so they don't count
+        t.members.iter().zip(aligns).any(|(m, &align)| {
+            m.bit_size == 0
+                && !self.is_zero_width_bitfield(m)
+                && align != 0
+                && !matches!(self.type_by_id(m.type_id), BtfType::Int(int) if !int.is_full_width())
+                && m.bit_offset % (align * 8) != 0
+        })
+    }
+
+    /// Returns vars contained in a given datasec, along with their type IDs. Non-var entries
+    /// (e.g., extern funcs in .ksyms) are skipped.
+    pub fn datasec_vars(&self, t: &BtfDatasec) -> Vec<(u32, &BtfVar<'_>)> {
+        t.vars
+            .iter()
+            .filter_map(|v| match self.type_by_id(v.type_id) {
+                BtfType::Var(var) => Some((v.type_id, var)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Follows const/volatile/restrict modifiers and type tags, returning ID of the first type
+    /// that is none of those.
     pub fn skip_mods(&self, mut type_id: u32) -> u32 {
         loop {
             match self.type_by_id(type_id) {
@@ -1018,6 +1722,7 @@ impl<'a> Btf<'a> {
         }
     }
 
+    /// Same as `skip_mods()`, but follows typedefs as well.
     pub fn skip_mods_and_typedefs(&self, mut type_id: u32) -> u32 {
         loop {
             match self.type_by_id(type_id) {
@@ -1031,93 +1736,597 @@ impl<'a> Btf<'a> {
         }
     }
 
+    /// Resolves type to the underlying concrete type, stripping all modifiers, type tags, and
+    /// typedefs, similarly to libbpf's `btf__resolve_type()`.
+    pub fn resolve_type(&self, type_id: u32) -> u32 {
+        self.skip_mods_and_typedefs(type_id)
+    }
+
+    /// Whether member is an unnamed zero-width bitfield (e.g., `int :0;`), which only forces the
+    /// next bitfield to start a new storage unit, as opposed to a regular field
+    pub fn is_zero_width_bitfield(&self, m: &BtfMember) -> bool {
+        m.name.is_empty()
+            && m.bit_size == 0
+            && matches!(
+                self.type_by_id(self.skip_mods_and_typedefs(m.type_id)),
+                BtfType::Int(_) | BtfType::Enum(_) | BtfType::Enum64(_)
+            )
+    }
+
+    /// Attributes types to source files they likely come from, based on .BTF.ext line info.
+    /// Each func is attributed to the file of the first line info record at func's start;
+    /// types used by funcs (directly or not) are attributed to the file of the first func (by
+    /// type ID) using them. Types not reachable from any such func are not present in the map.
+    pub fn type_source_files(&self) -> HashMap<u32, &'a str> {
+        let mut func_files = BTreeMap::new();
+        for fsec in &self.func_secs {
+            let lsec = match self.line_secs.iter().find(|l| l.name == fsec.name) {
+                Some(lsec) => lsec,
+                None => continue,
+            };
+            for f in &fsec.recs {
+                if let Some(l) = lsec.recs.iter().find(|l| l.insn_off == f.insn_off) {
+                    func_files.insert(f.type_id, l.file_name);
+                }
+            }
+        }
+        let mut files = HashMap::new();
+        for (&func_id, &file) in &func_files {
+            for id in self.reachable_from(std::iter::once(func_id)) {
+                if id != 0 {
+                    files.entry(id).or_insert(file);
+                }
+            }
+        }
+        files
+    }
+
+    /// Decl tags attached to a given type or to its members/params, in type ID order.
+    pub fn decl_tags_of(&self, type_id: u32) -> Vec<&BtfDeclTag<'a>> {
+        self.iter()
+            .filter_map(|(_, t)| match t {
+                BtfType::DeclTag(t) if t.type_id == type_id => Some(t),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns IDs of types given type references directly, in the order they appear in it
+    /// (e.g., index type before element type for arrays, result type before params for func
+    /// protos), including duplicates. Void is referenced as ID 0, e.g., by `void *` pointers or
+    /// funcs without result.
+    pub fn referenced_types(&self, id: u32) -> Vec<u32> {
+        let mut ids = Vec::new();
+        self.type_by_id(id).visit_type_ids(|id| ids.push(id));
+        ids
+    }
+
+    /// Returns given types along with all the types they reference, directly or indirectly.
+    pub fn reachable_from<I: IntoIterator<Item = u32>>(&self, ids: I) -> BTreeSet<u32> {
+        let mut seen = BTreeSet::new();
+        let mut queue: Vec<u32> = ids.into_iter().collect();
+        while let Some(id) = queue.pop() {
+            if seen.insert(id) {
+                self.type_by_id(id).visit_type_ids(|id| queue.push(id));
+            }
+        }
+        seen
+    }
+
+    /// Builds BTF containing only given types and types they depend on, renumbered to be
+    /// contiguous (preserving their relative order). .BTF.ext data is not carried over. String
+    /// section is shared with original BTF and is pruned only on serialization.
+    pub fn subset(&self, ids: &BTreeSet<u32>) -> BtfResult<Btf<'a>> {
+        if let Some(&id) = ids.iter().find(|&&id| id >= self.type_cnt()) {
+            return btf_error(format!(
+                "Type ID {} is out of bounds, there are only {} types",
+                id,
+                self.type_cnt()
+            ));
+        }
+        let keep = self.reachable_from(ids.iter().copied());
+        let mut id_map = vec![0; self.type_cnt() as usize];
+        let mut types = vec![BtfType::Void];
+        for &id in keep.iter().filter(|&&id| id != 0) {
+            id_map[id as usize] = types.len() as u32;
+            types.push(self.type_by_id(id).clone());
+        }
+        for t in &mut types {
+            t.remap_type_ids(|id| id_map[id as usize]);
+        }
+        Ok(self.with_types(types))
+    }
+
+    /// Same as `subset()`, but takes root types as a slice, e.g., to extract minimal BTF
+    /// describing just the types BPF program uses. Void stays ID 0 and every reference is
+    /// rewritten to new IDs.
+    pub fn prune(&self, roots: &[u32]) -> BtfResult<Btf<'a>> {
+        self.subset(&roots.iter().copied().collect())
+    }
+
+    /// BTF consisting of given types only (the first of which has to be void), without string
+    /// section, as produced by `BtfBuilder`
+    pub(crate) fn from_types(
+        types: Vec<BtfType<'a>>,
+        ptr_sz: u32,
+        endian: scroll::Endian,
+    ) -> Btf<'a> {
+        let mut btf = Btf {
+            endian,
+            version: BTF_VERSION,
+            flags: 0,
+            types,
+            strs: StringTable::new(&[]),
+            ptr_sz,
+            base: None,
+            base_type_cnt: 0,
+            name_index: HashMap::new(),
+            aligns: OnceLock::new(),
+            has_ext: false,
+            func_secs: Vec::new(),
+            line_secs: Vec::new(),
+            core_reloc_secs: Vec::new(),
+        };
+        btf.index_names(1);
+        btf
+    }
+
+    /// Builds BTF with the same properties and string section, but with given types instead.
+    /// .BTF.ext data is not carried over.
+    pub(crate) fn with_types(&self, types: Vec<BtfType<'a>>) -> Btf<'a> {
+        let mut btf = Btf {
+            endian: self.endian,
+            version: self.version,
+            flags: self.flags,
+            types,
+            strs: self.strs,
+            ptr_sz: self.ptr_sz,
+            base: None,
+            base_type_cnt: 0,
+            name_index: HashMap::new(),
+            aligns: OnceLock::new(),
+            has_ext: false,
+            func_secs: Vec::new(),
+            line_secs: Vec::new(),
+            core_reloc_secs: Vec::new(),
+        };
+        btf.index_names(1);
+        btf
+    }
+
     pub fn load(elf: &object::File<'a>) -> BtfResult<Btf<'a>> {
         let endian = if elf.is_little_endian() {
             scroll::LE
         } else {
             scroll::BE
         };
+        let btf_section = elf
+            .section_by_name(BTF_ELF_SEC)
+            .ok_or_else(|| BtfError::MissingSection(BTF_ELF_SEC.to_owned()))?;
+        let data = btf_section.data().map_err(BtfError::from)?;
+        let mut btf = Btf::load_from_bytes(data, if elf.is_64() { 8 } else { 4 }, endian)?;
+        if let Some(ext_section) = elf.section_by_name(BTF_EXT_ELF_SEC) {
+            let ext = btf.load_ext_data(ext_section.data().map_err(BtfError::from)?)?;
+            btf.has_ext = true;
+            btf.func_secs = ext.func_secs;
+            btf.line_secs = ext.line_secs;
+            btf.core_reloc_secs = ext.core_reloc_secs;
+        }
+
+        Ok(btf)
+    }
+
+    /// Loads .BTF.ext records of given ELF file, grouped by ELF section (i.e., BPF program) they
+    /// describe, or returns `None` if there is no .BTF.ext section. Records reference types and
+    /// strings of this BTF, so it has to be loaded from .BTF section of the same ELF file.
+    pub fn load_ext(&self, elf: &object::File<'a>) -> BtfResult<Option<BtfExt<'a>>> {
+        let ext_section = match elf.section_by_name(BTF_EXT_ELF_SEC) {
+            Some(sec) => sec,
+            None => return Ok(None),
+        };
+        let ext = self.load_ext_data(ext_section.data().map_err(BtfError::from)?)?;
+        Ok(Some(BtfExt {
+            func_infos: by_sec_name(ext.func_secs),
+            line_infos: by_sec_name(ext.line_secs),
+            core_relos: by_sec_name(ext.core_reloc_secs),
+        }))
+    }
+
+    fn load_ext_data(&self, ext_data: &'a [u8]) -> BtfResult<ExtSecs<'a>> {
+        let endian = self.endian;
+        let ext_hdr = ext_data.pread_with::<btf_ext_header_v1>(0, endian)?;
+        if ext_hdr.magic != BTF_MAGIC {
+            return Err(Box::new(BtfError::InvalidMagic(ext_hdr.magic)));
+        }
+        if ext_hdr.version != BTF_VERSION {
+            return Err(Box::new(BtfError::UnsupportedVersion(ext_hdr.version)));
+        }
+        let ext_hdr2 = if ext_hdr.hdr_len >= size_of::<btf_ext_header_v2>() as u32 {
+            Some(ext_data.pread_with::<btf_ext_header_v2>(0, endian)?)
+        } else {
+            None
+        };
+        // sub-section offsets are relative to the end of the header
+        let subsec = |what: &str, off: u32, len: u32| {
+            let start = ext_hdr.hdr_len as usize + off as usize;
+            match ext_data.get(start..start + len as usize) {
+                Some(data) => Ok(data),
+                None => btf_error(format!(".BTF.ext {} data is out of bounds", what)),
+            }
+        };
+        let mut ext = ExtSecs::default();
+        if ext_hdr.func_info_len > 0 {
+            let func_data = subsec("func info", ext_hdr.func_info_off, ext_hdr.func_info_len)?;
+            ext.func_secs = self.load_func_secs(func_data)?;
+        }
+        if ext_hdr.line_info_len > 0 {
+            let line_data = subsec("line info", ext_hdr.line_info_off, ext_hdr.line_info_len)?;
+            ext.line_secs = self.load_line_secs(line_data)?;
+        }
+        if let Some(h) = ext_hdr2 {
+            if h.core_reloc_len > 0 {
+                let reloc_data = subsec("CO-RE reloc", h.core_reloc_off, h.core_reloc_len)?;
+                ext.core_reloc_secs = self.load_core_reloc_secs(reloc_data)?;
+            }
+        }
+        Ok(ext)
+    }
+
+    /// Loads BTF (and .BTF.ext, if present) from ELF file contents, e.g., BPF object file read
+    /// into memory, without having to parse ELF with `object` crate first. See `load()`.
+    pub fn from_elf_bytes(data: &'a [u8]) -> BtfResult<Btf<'a>> {
+        let elf = object::File::parse(data).map_err(BtfError::from)?;
+        Btf::load(&elf)
+    }
+
+    /// Loads and merges all BTF data found in ELF file: each `.BTF` and `.BTF.<suffix>` section
+    /// (except for .BTF.ext), as well as multiple BTF blobs concatenated within one section, as
+    /// done by `ld -r`. Blobs that fail to parse are skipped and reported along with resulting
+    /// BTF. It's an error only if no BTF could be loaded at all. .BTF.ext data is not loaded.
+    pub fn load_all_sections(
+        elf: &object::File<'a>,
+    ) -> BtfResult<(Btf<'a>, Vec<SectionLoadError>)> {
+        let endian = if elf.is_little_endian() {
+            scroll::LE
+        } else {
+            scroll::BE
+        };
+        let ptr_sz = if elf.is_64() { 8 } else { 4 };
+        let mut btf: Option<Btf<'a>> = None;
+        let mut errs = Vec::new();
+        for sec in elf.sections() {
+            let name = match sec.name() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            if name != BTF_ELF_SEC && (!name.starts_with(".BTF.") || name == BTF_EXT_ELF_SEC) {
+                continue;
+            }
+            let data = match sec.data() {
+                Ok(d) => d,
+                Err(e) => {
+                    errs.push(SectionLoadError {
+                        section: name.to_owned(),
+                        offset: 0,
+                        error: Box::new(e),
+                    });
+                    continue;
+                }
+            };
+            let mut off = 0;
+            while off < data.len() {
+                let blob = match Btf::raw_blob_len(&data[off..], endian) {
+                    Ok(len) => &data[off..off + len],
+                    Err(e) => {
+                        // can't find where next blob starts, give up on the rest of section
+                        errs.push(SectionLoadError {
+                            section: name.to_owned(),
+                            offset: off,
+                            error: e,
+                        });
+                        break;
+                    }
+                };
+                match Btf::load_from_bytes(blob, ptr_sz, endian) {
+                    Ok(b) => match &mut btf {
+                        Some(btf) => {
+                            btf.merge(b);
+                        }
+                        None => btf = Some(b),
+                    },
+                    Err(e) => errs.push(SectionLoadError {
+                        section: name.to_owned(),
+                        offset: off,
+                        error: e,
+                    }),
+                }
+                off += blob.len();
+            }
+        }
+        match btf {
+            Some(btf) => Ok((btf, errs)),
+            None if errs.is_empty() => {
+                Err(Box::new(BtfError::MissingSection(BTF_ELF_SEC.to_owned())))
+            }
+            None => btf_error(format!(
+                "Failed to load any BTF data: {}",
+                errs.iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )),
+        }
+    }
+
+    /// Appends all types of `other` BTF after existing ones, remapping type IDs they reference
+    /// accordingly. Returns the offset added to `other`'s type IDs. `other`'s .BTF.ext data is
+    /// dropped.
+    pub fn merge(&mut self, other: Btf<'a>) -> u32 {
+        let id_off = self.type_cnt() - 1;
+        for mut t in other.types.into_iter().skip(1) {
+            t.remap_type_ids(|id| if id == 0 { 0 } else { id + id_off });
+            self.types.push(t);
+        }
+        self.index_names(id_off + 1);
+        self.aligns = OnceLock::new();
+        id_off
+    }
+
+    /// Adds named types starting from `start_id` to name index. Base types of split BTF are
+    /// looked up in base BTF's own index instead.
+    fn index_names(&mut self, start_id: u32) {
+        for id in max(start_id, self.base_type_cnt)..self.type_cnt() {
+            let t = &self.types[(id - self.base_type_cnt) as usize];
+            if !t.name().is_empty() {
+                self.name_index
+                    .entry((t.kind(), t.name()))
+                    .or_default()
+                    .push(id);
+            }
+        }
+    }
+
+    /// Determines total length of raw BTF data (header, types and strings) starting at the
+    /// beginning of `data`.
+    fn raw_blob_len(data: &[u8], endian: scroll::Endian) -> BtfResult<usize> {
+        let hdr = data.pread_with::<btf_header>(0, endian)?;
+        if hdr.magic != BTF_MAGIC {
+            return Err(Box::new(BtfError::InvalidMagic(hdr.magic)));
+        }
+        // header can be extended with fields unknown to us, which are skipped, as type and
+        // string section offsets are relative to the end of the whole header
+        if (hdr.hdr_len as usize) < size_of::<btf_header>() {
+            return btf_error(format!(
+                "BTF header length {} is less than {}",
+                hdr.hdr_len,
+                size_of::<btf_header>()
+            ));
+        }
+        let mut len = hdr.hdr_len as usize;
+        for (section, off, sec_len) in [
+            ("type", hdr.type_off, hdr.type_len),
+            ("string", hdr.str_off, hdr.str_len),
+        ] {
+            let end = hdr.hdr_len as usize + off as usize + sec_len as usize;
+            if end > data.len() {
+                return Err(Box::new(BtfError::SectionOutOfBounds {
+                    section,
+                    end,
+                    data_len: data.len(),
+                }));
+            }
+            len = max(len, end);
+        }
+        Ok(len)
+    }
+
+    /// Loads BTF from a file, detecting its format from its contents. Supported are ELF files
+    /// with .BTF section, raw BTF data (e.g., /sys/kernel/btf/vmlinux), and gzip-compressed
+    /// variants of either of those. Resulting BTF owns file contents.
+    pub fn from_path(path: &Path) -> BtfResult<OwnedBtf<'static>> {
+        OwnedBtf::new(Btf::read_file(path)?, |data| {
+            Btf::from_any_bytes(data).unwrap_or_else(|| {
+                btf_error(format!("Unrecognized BTF file format: {}", path.display()))
+            })
+        })
+    }
+
+    /// Same as `from_path()`, but takes file contents that are already in memory. Resulting BTF
+    /// owns the buffer, so it doesn't borrow from anything and can be stored, cached, and shared
+    /// between threads freely.
+    pub fn from_owned_bytes(data: Vec<u8>) -> BtfResult<OwnedBtf<'static>> {
+        OwnedBtf::new(Btf::decompress(data)?, |data| {
+            Btf::from_any_bytes(data)
+                .unwrap_or_else(|| btf_error("Unrecognized BTF data format".to_owned()))
+        })
+    }
+
+    /// Loads BTF from ELF or raw BTF data, or returns `None`, if it's neither.
+    fn from_any_bytes(data: &'a [u8]) -> Option<BtfResult<Btf<'a>>> {
+        if data.starts_with(ELF_MAGIC) {
+            Some(Btf::from_elf_bytes(data))
+        } else if data.starts_with(&BTF_MAGIC.to_le_bytes()) {
+            Some(Btf::load_from_bytes(
+                data,
+                size_of::<usize>() as u32,
+                scroll::LE,
+            ))
+        } else if data.starts_with(&BTF_MAGIC.to_be_bytes()) {
+            Some(Btf::load_from_bytes(
+                data,
+                size_of::<usize>() as u32,
+                scroll::BE,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Loads split BTF on top of `base` from a file, which is either raw BTF data (e.g.,
+    /// /sys/kernel/btf/<module>) or ELF with .BTF section (e.g., kernel module's .ko file),
+    /// possibly gzip-compressed. See `load_split()`.
+    pub fn from_path_split(base: &'a Btf<'a>, path: &Path) -> BtfResult<OwnedBtf<'a>> {
+        OwnedBtf::new(Btf::read_file(path)?, |mut data| {
+            if data.starts_with(ELF_MAGIC) {
+                let elf = object::File::parse(data).map_err(BtfError::from)?;
+                let btf_section = elf
+                    .section_by_name(BTF_ELF_SEC)
+                    .ok_or_else(|| BtfError::MissingSection(BTF_ELF_SEC.to_owned()))?;
+                data = btf_section.data().map_err(BtfError::from)?;
+            }
+            Btf::load_split(base, data)
+        })
+    }
+
+    /// Reads whole file, decompressing it, if it's gzip-compressed.
+    fn read_file(path: &Path) -> BtfResult<Vec<u8>> {
+        Btf::decompress(std::fs::read(path).map_err(BtfError::Io)?)
+    }
+
+    /// Decompresses data, if it's gzip-compressed, otherwise returns it as is.
+    fn decompress(data: Vec<u8>) -> BtfResult<Vec<u8>> {
+        if !data.starts_with(GZIP_MAGIC) {
+            return Ok(data);
+        }
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&data[..])
+            .read_to_end(&mut decompressed)
+            .map_err(BtfError::Io)?;
+        Ok(decompressed)
+    }
+
+    /// Loads BTF of the running kernel from /sys/kernel/btf/vmlinux. Like with `from_path()`,
+    /// resulting BTF owns file contents.
+    pub fn load_from_sys() -> BtfResult<OwnedBtf<'static>> {
+        let data = match std::fs::read(VMLINUX_BTF_PATH) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return btf_error(format!(
+                    "{} not found, kernel is probably built without CONFIG_DEBUG_INFO_BTF",
+                    VMLINUX_BTF_PATH
+                ));
+            }
+            Err(e) => return Err(Box::new(BtfError::Io(e))),
+        };
+        OwnedBtf::new(data, |data| {
+            Btf::load_from_bytes(data, size_of::<usize>() as u32, scroll::NATIVE)
+        })
+    }
+
+    /// Parses raw BTF data (header, types, and strings), not wrapped into ELF, e.g., contents
+    /// of /sys/kernel/btf/vmlinux. `ptr_sz` is the pointer size of the target BTF describes.
+    pub fn load_from_bytes(
+        data: &'a [u8],
+        ptr_sz: u32,
+        endian: scroll::Endian,
+    ) -> BtfResult<Btf<'a>> {
+        Btf::load_bytes(data, ptr_sz, endian, None)
+    }
+
+    /// Parses raw split BTF data (e.g., kernel module's BTF from /sys/kernel/btf/<module>),
+    /// which continues type ID and string offset spaces of `base` BTF. Resulting BTF refers to
+    /// `base` for base types, which come before split BTF's own types, so type IDs resolve the
+    /// same way they do in the kernel; own types start at `base_type_cnt()`.
+    pub fn load_split(base: &'a Btf<'a>, data: &'a [u8]) -> BtfResult<Btf<'a>> {
+        if base.base_type_cnt != 0 {
+            return btf_error("Base BTF can't be split BTF itself".to_owned());
+        }
+        Btf::load_bytes(data, base.ptr_sz, base.endian, Some(base))
+    }
+
+    fn load_bytes(
+        data: &'a [u8],
+        ptr_sz: u32,
+        endian: scroll::Endian,
+        base: Option<&'a Btf<'a>>,
+    ) -> BtfResult<Btf<'a>> {
         let mut btf = Btf::<'a> {
-            endian: endian,
-            ptr_sz: if elf.is_64() { 8 } else { 4 },
-            types: vec![BtfType::Void],
+            endian,
+            version: 0,
+            flags: 0,
+            ptr_sz,
+            types: match base {
+                Some(_) => Vec::new(),
+                None => vec![BtfType::Void],
+            },
+            strs: StringTable::new(&[]),
+            base,
+            base_type_cnt: base.map_or(0, |base| base.type_cnt()),
+            name_index: HashMap::new(),
+            aligns: OnceLock::new(),
             has_ext: false,
             func_secs: Vec::new(),
             line_secs: Vec::new(),
             core_reloc_secs: Vec::new(),
         };
 
-        let btf_section = elf
-            .section_by_name(BTF_ELF_SEC)
-            .ok_or_else(|| Box::new(BtfError::new("No .BTF section found!")))?;
-        let data = match btf_section.data() {
-            Ok(d) => d,
-            _ => panic!("expected borrowed data"),
-        };
         let hdr = data.pread_with::<btf_header>(0, endian)?;
         if hdr.magic != BTF_MAGIC {
-            return btf_error(format!("Invalid BTF magic: {}", hdr.magic));
+            return Err(Box::new(BtfError::InvalidMagic(hdr.magic)));
         }
         if hdr.version != BTF_VERSION {
-            return btf_error(format!(
-                "Unsupported BTF version: {}, expect: {}",
-                hdr.version, BTF_VERSION
-            ));
+            return Err(Box::new(BtfError::UnsupportedVersion(hdr.version)));
         }
+        btf.version = hdr.version;
+        btf.flags = hdr.flags;
+        // make sure type and string sections are within data bounds
+        Btf::raw_blob_len(data, endian)?;
 
         let str_off = (hdr.hdr_len + hdr.str_off) as usize;
         let str_data = &data[str_off..str_off + hdr.str_len as usize];
+        btf.strs = match base {
+            Some(base) => StringTable::new_split(base.strs.as_bytes(), str_data),
+            None => StringTable::new(str_data),
+        };
 
         let type_off = (hdr.hdr_len + hdr.type_off) as usize;
         let type_data = &data[type_off..type_off + hdr.type_len as usize];
         let mut off: usize = 0;
+        let mut last_off = 0;
         while off < hdr.type_len as usize {
-            let t = btf.load_type(&type_data[off..], str_data)?;
+            if type_data.len() - off < size_of::<btf_type>() {
+                return Err(Box::new(BtfError::TruncatedType { offset: off }));
+            }
+            // type's own data can only fail to parse by running past the end of type section
+            let t = btf
+                .load_type(&type_data[off..], &btf.strs)
+                .map_err(|e| -> Box<dyn Error> {
+                    if e.is::<scroll::Error>() {
+                        Box::new(BtfError::TruncatedType { offset: off })
+                    } else {
+                        e
+                    }
+                })?;
+            last_off = off;
             off += Btf::type_size(&t);
             btf.types.push(t);
         }
+        // last type's size running past the end of type section means either corrupted data or
+        // a bug in type_size() for that kind
+        if off != hdr.type_len as usize {
+            return Err(Box::new(BtfError::TruncatedType { offset: last_off }));
+        }
+        btf.validate_type_ids(max(btf.base_type_cnt, 1))?;
+        btf.index_names(1);
+        Ok(btf)
+    }
 
-        if let Some(ext_section) = elf.section_by_name(BTF_EXT_ELF_SEC) {
-            btf.has_ext = true;
-            let ext_data = match ext_section.data() {
-                Ok(d) => d,
-                _ => panic!("expected borrowed data"),
-            };
-            let ext_hdr = ext_data.pread_with::<btf_ext_header_v1>(0, endian)?;
-            if ext_hdr.magic != BTF_MAGIC {
-                return btf_error(format!("Invalid .BTF.ext magic: {}", ext_hdr.magic));
-            }
-            if ext_hdr.version != BTF_VERSION {
-                return btf_error(format!(
-                    "Unsupported .BTF.ext version: {}, expect: {}",
-                    ext_hdr.version, BTF_VERSION
-                ));
-            }
-            let ext_hdr2 = if ext_hdr.hdr_len >= size_of::<btf_ext_header_v2>() as u32 {
-                Some(ext_data.pread_with::<btf_ext_header_v2>(0, endian)?)
-            } else {
-                None
-            };
-            if ext_hdr.func_info_len > 0 {
-                let func_off = (ext_hdr.hdr_len + ext_hdr.func_info_off) as usize;
-                let func_data = &ext_data[func_off..func_off + ext_hdr.func_info_len as usize];
-                btf.func_secs = btf.load_func_secs(func_data, str_data)?;
-            }
-            if ext_hdr.line_info_len > 0 {
-                let line_off = (ext_hdr.hdr_len + ext_hdr.line_info_off) as usize;
-                let line_data = &ext_data[line_off..line_off + ext_hdr.line_info_len as usize];
-                btf.line_secs = btf.load_line_secs(line_data, str_data)?;
-            }
-            if let Some(h) = ext_hdr2 {
-                if h.core_reloc_len > 0 {
-                    let reloc_off = (h.hdr_len + h.core_reloc_off) as usize;
-                    let reloc_data = &ext_data[reloc_off..reloc_off + h.core_reloc_len as usize];
-                    btf.core_reloc_secs = btf.load_core_reloc_secs(reloc_data, str_data)?;
+    /// Checks that types starting from `start_id` reference only existing types, so that
+    /// `type_by_id()` can't panic on malformed BTF later on.
+    pub(crate) fn validate_type_ids(&self, start_id: u32) -> BtfResult<()> {
+        let type_cnt = self.type_cnt();
+        for id in start_id..type_cnt {
+            let mut bad_id = None;
+            self.type_by_id(id).visit_type_ids(|type_id| {
+                if type_id >= type_cnt && bad_id.is_none() {
+                    bad_id = Some(type_id);
                 }
+            });
+            if let Some(type_id) = bad_id {
+                return Err(Box::new(BtfError::BadTypeId { id, type_id }));
             }
         }
-
-        Ok(btf)
+        Ok(())
     }
 
     pub fn type_size(t: &BtfType) -> usize {
@@ -1138,12 +2347,13 @@ impl<'a> Btf<'a> {
             BtfType::Struct(t) => common + t.members.len() * size_of::<btf_member>(),
             BtfType::Union(t) => common + t.members.len() * size_of::<btf_member>(),
             BtfType::Enum(t) => common + t.values.len() * size_of::<btf_enum>(),
+            BtfType::Enum64(t) => common + t.values.len() * size_of::<btf_enum64>(),
             BtfType::FuncProto(t) => common + t.params.len() * size_of::<btf_param>(),
             BtfType::Datasec(t) => common + t.vars.len() * size_of::<btf_datasec_var>(),
         }
     }
 
-    fn load_type(&self, data: &'a [u8], strs: &'a [u8]) -> BtfResult<BtfType<'a>> {
+    fn load_type(&self, data: &'a [u8], strs: &StringTable<'a>) -> BtfResult<BtfType<'a>> {
         let t = data.pread_with::<btf_type>(0, self.endian)?;
         let extra = &data[size_of::<btf_type>()..];
         let kind = Btf::get_kind(t.info);
@@ -1154,16 +2364,17 @@ impl<'a> Btf<'a> {
             BTF_KIND_STRUCT => self.load_struct(&t, extra, strs),
             BTF_KIND_UNION => self.load_union(&t, extra, strs),
             BTF_KIND_ENUM => self.load_enum(&t, extra, strs),
+            BTF_KIND_ENUM64 => self.load_enum64(&t, extra, strs),
             BTF_KIND_FWD => self.load_fwd(&t, strs),
             BTF_KIND_TYPEDEF => Ok(BtfType::Typedef(BtfTypedef {
-                name: Btf::get_btf_str(strs, t.name_off)?,
+                name: strs.get(t.name_off)?,
                 type_id: t.type_id,
             })),
             BTF_KIND_VOLATILE => Ok(BtfType::Volatile(BtfVolatile { type_id: t.type_id })),
             BTF_KIND_CONST => Ok(BtfType::Const(BtfConst { type_id: t.type_id })),
             BTF_KIND_RESTRICT => Ok(BtfType::Restrict(BtfRestrict { type_id: t.type_id })),
             BTF_KIND_FUNC => Ok(BtfType::Func(BtfFunc {
-                name: Btf::get_btf_str(strs, t.name_off)?,
+                name: strs.get(t.name_off)?,
                 proto_type_id: t.type_id,
                 kind: match Btf::get_vlen(t.info) {
                     BTF_FUNC_STATIC => BtfFuncKind::Static,
@@ -1176,25 +2387,31 @@ impl<'a> Btf<'a> {
             BTF_KIND_VAR => self.load_var(&t, extra, strs),
             BTF_KIND_DATASEC => self.load_datasec(&t, extra, strs),
             BTF_KIND_FLOAT => Ok(BtfType::Float(BtfFloat {
-                name: Btf::get_btf_str(strs, t.name_off)?,
+                name: strs.get(t.name_off)?,
                 sz: t.type_id,
             })),
             BTF_KIND_DECL_TAG => self.load_decl_tag(&t, extra, strs),
             BTF_KIND_TYPE_TAG => Ok(BtfType::TypeTag(BtfTypeTag {
-                name: Btf::get_btf_str(strs, t.name_off)?,
+                name: strs.get(t.name_off)?,
                 type_id: t.type_id,
             })),
-            _ => btf_error(format!("Unknown BTF kind: {}", kind)),
+            _ => Err(Box::new(BtfError::UnknownKind(kind))),
         }
     }
 
-    fn load_int(&self, t: &btf_type, extra: &'a [u8], strs: &'a [u8]) -> BtfResult<BtfType<'a>> {
+    fn load_int(
+        &self,
+        t: &btf_type,
+        extra: &'a [u8],
+        strs: &StringTable<'a>,
+    ) -> BtfResult<BtfType<'a>> {
         let info = extra.pread_with::<u32>(0, self.endian)?;
         let enc = (info >> 24) & 0xf;
         let off = (info >> 16) & 0xff;
         let bits = info & 0xff;
         Ok(BtfType::Int(BtfInt {
-            name: Btf::get_btf_str(strs, t.name_off)?,
+            name: strs.get(t.name_off)?,
+            sz: t.type_id,
             bits: bits,
             offset: off,
             encoding: match enc {
@@ -1218,19 +2435,29 @@ impl<'a> Btf<'a> {
         }))
     }
 
-    fn load_struct(&self, t: &btf_type, extra: &'a [u8], strs: &'a [u8]) -> BtfResult<BtfType<'a>> {
+    fn load_struct(
+        &self,
+        t: &btf_type,
+        extra: &'a [u8],
+        strs: &StringTable<'a>,
+    ) -> BtfResult<BtfType<'a>> {
         Ok(BtfType::Struct(BtfComposite {
             is_struct: true,
-            name: Btf::get_btf_str(strs, t.name_off)?,
+            name: strs.get(t.name_off)?,
             sz: t.type_id, // it's a type/size union in C
             members: self.load_members(t, extra, strs)?,
         }))
     }
 
-    fn load_union(&self, t: &btf_type, extra: &'a [u8], strs: &'a [u8]) -> BtfResult<BtfType<'a>> {
+    fn load_union(
+        &self,
+        t: &btf_type,
+        extra: &'a [u8],
+        strs: &StringTable<'a>,
+    ) -> BtfResult<BtfType<'a>> {
         Ok(BtfType::Union(BtfComposite {
             is_struct: false,
-            name: Btf::get_btf_str(strs, t.name_off)?,
+            name: strs.get(t.name_off)?,
             sz: t.type_id, // it's a type/size union in C
             members: self.load_members(t, extra, strs)?,
         }))
@@ -1240,7 +2467,7 @@ impl<'a> Btf<'a> {
         &self,
         t: &btf_type,
         extra: &'a [u8],
-        strs: &'a [u8],
+        strs: &StringTable<'a>,
     ) -> BtfResult<Vec<BtfMember<'a>>> {
         let mut res = Vec::new();
         let mut off: usize = 0;
@@ -1249,7 +2476,7 @@ impl<'a> Btf<'a> {
         for _ in 0..Btf::get_vlen(t.info) {
             let m = extra.pread_with::<btf_member>(off, self.endian)?;
             res.push(BtfMember {
-                name: Btf::get_btf_str(strs, m.name_off)?,
+                name: strs.get(m.name_off)?,
                 type_id: m.type_id,
                 bit_size: if bits { (m.offset >> 24) as u8 } else { 0 },
                 bit_offset: if bits { m.offset & 0xffffff } else { m.offset },
@@ -1259,28 +2486,61 @@ impl<'a> Btf<'a> {
         Ok(res)
     }
 
-    fn load_enum(&self, t: &btf_type, extra: &'a [u8], strs: &'a [u8]) -> BtfResult<BtfType<'a>> {
+    fn load_enum(
+        &self,
+        t: &btf_type,
+        extra: &'a [u8],
+        strs: &StringTable<'a>,
+    ) -> BtfResult<BtfType<'a>> {
         let mut vals = Vec::new();
         let mut off: usize = 0;
 
         for _ in 0..Btf::get_vlen(t.info) {
             let v = extra.pread_with::<btf_enum>(off, self.endian)?;
             vals.push(BtfEnumValue {
-                name: Btf::get_btf_str(strs, v.name_off)?,
-                value: v.val,
+                name: strs.get(v.name_off)?,
+                value: v.val as i64,
             });
             off += size_of::<btf_enum>();
         }
         Ok(BtfType::Enum(BtfEnum {
-            name: Btf::get_btf_str(strs, t.name_off)?,
+            name: strs.get(t.name_off)?,
             sz: t.type_id, // it's a type/size union in C
+            is_64: false,
+            is_signed: Btf::get_kind_flag(t.info),
             values: vals,
         }))
     }
 
-    fn load_fwd(&self, t: &btf_type, strs: &'a [u8]) -> BtfResult<BtfType<'a>> {
+    fn load_enum64(
+        &self,
+        t: &btf_type,
+        extra: &'a [u8],
+        strs: &StringTable<'a>,
+    ) -> BtfResult<BtfType<'a>> {
+        let mut vals = Vec::new();
+        let mut off: usize = 0;
+
+        for _ in 0..Btf::get_vlen(t.info) {
+            let v = extra.pread_with::<btf_enum64>(off, self.endian)?;
+            vals.push(BtfEnumValue {
+                name: strs.get(v.name_off)?,
+                value: ((v.val_hi32 as u64) << 32 | v.val_lo32 as u64) as i64,
+            });
+            off += size_of::<btf_enum64>();
+        }
+        Ok(BtfType::Enum64(BtfEnum {
+            name: strs.get(t.name_off)?,
+            sz: t.type_id, // it's a type/size union in C
+            is_64: true,
+            is_signed: Btf::get_kind_flag(t.info),
+            values: vals,
+        }))
+    }
+
+    fn load_fwd(&self, t: &btf_type, strs: &StringTable<'a>) -> BtfResult<BtfType<'a>> {
         Ok(BtfType::Fwd(BtfFwd {
-            name: Btf::get_btf_str(strs, t.name_off)?,
+            name: strs.get(t.name_off)?,
             kind: if Btf::get_kind_flag(t.info) {
                 BtfFwdKind::Union
             } else {
@@ -1293,7 +2553,7 @@ impl<'a> Btf<'a> {
         &self,
         t: &btf_type,
         extra: &'a [u8],
-        strs: &'a [u8],
+        strs: &StringTable<'a>,
     ) -> BtfResult<BtfType<'a>> {
         let mut params = Vec::new();
         let mut off: usize = 0;
@@ -1301,7 +2561,7 @@ impl<'a> Btf<'a> {
         for _ in 0..Btf::get_vlen(t.info) {
             let p = extra.pread_with::<btf_param>(off, self.endian)?;
             params.push(BtfFuncParam {
-                name: Btf::get_btf_str(strs, p.name_off)?,
+                name: strs.get(p.name_off)?,
                 type_id: p.type_id,
             });
             off += size_of::<btf_param>();
@@ -1312,10 +2572,15 @@ impl<'a> Btf<'a> {
         }))
     }
 
-    fn load_var(&self, t: &btf_type, extra: &'a [u8], strs: &'a [u8]) -> BtfResult<BtfType<'a>> {
+    fn load_var(
+        &self,
+        t: &btf_type,
+        extra: &'a [u8],
+        strs: &StringTable<'a>,
+    ) -> BtfResult<BtfType<'a>> {
         let kind = extra.pread_with::<u32>(0, self.endian)?;
         Ok(BtfType::Var(BtfVar {
-            name: Btf::get_btf_str(strs, t.name_off)?,
+            name: strs.get(t.name_off)?,
             type_id: t.type_id,
             kind: match kind {
                 BTF_VAR_STATIC => BtfVarKind::Static,
@@ -1332,7 +2597,7 @@ impl<'a> Btf<'a> {
         &self,
         t: &btf_type,
         extra: &'a [u8],
-        strs: &'a [u8],
+        strs: &StringTable<'a>,
     ) -> BtfResult<BtfType<'a>> {
         let mut vars = Vec::new();
         let mut off: usize = 0;
@@ -1347,7 +2612,7 @@ impl<'a> Btf<'a> {
             off += size_of::<btf_datasec_var>();
         }
         Ok(BtfType::Datasec(BtfDatasec {
-            name: Btf::get_btf_str(strs, t.name_off)?,
+            name: strs.get(t.name_off)?,
             sz: t.type_id, // it's a type/size union in C
             vars: vars,
         }))
@@ -1357,11 +2622,11 @@ impl<'a> Btf<'a> {
         &self,
         t: &btf_type,
         extra: &'a [u8],
-        strs: &'a [u8],
+        strs: &StringTable<'a>,
     ) -> BtfResult<BtfType<'a>> {
-        let comp_idx = extra.pread_with::<u32>(0, self.endian)?;
+        let comp_idx = extra.pread_with::<i32>(0, self.endian)?;
         Ok(BtfType::DeclTag(BtfDeclTag {
-            name: Btf::get_btf_str(strs, t.name_off)?,
+            name: strs.get(t.name_off)?,
             type_id: t.type_id,
             comp_idx: comp_idx,
         }))
@@ -1379,111 +2644,39 @@ impl<'a> Btf<'a> {
         (info >> 31) == 1
     }
 
-    fn load_func_secs(
-        &self,
-        mut data: &'a [u8],
-        strs: &'a [u8],
-    ) -> BtfResult<Vec<BtfExtSection<'a, BtfExtFunc>>> {
-        let rec_sz = data.pread_with::<u32>(0, self.endian)?;
-        if rec_sz < size_of::<btf_ext_func_info>() as u32 {
-            return btf_error(format!(
-                "Too small func info record size: {}, expect at least: {}",
-                rec_sz,
-                size_of::<btf_ext_func_info>()
-            ));
-        }
-
-        data = &data[size_of::<u32>()..];
-        let mut secs = Vec::new();
-        while !data.is_empty() {
-            let sec_hdr = data.pread_with::<btf_ext_info_sec>(0, self.endian)?;
-            data = &data[size_of::<btf_ext_info_sec>()..];
-
-            let mut recs = Vec::new();
-            for i in 0..sec_hdr.num_info {
-                let off = (i * rec_sz) as usize;
-                let rec = data.pread_with::<btf_ext_func_info>(off, self.endian)?;
-                recs.push(BtfExtFunc {
-                    insn_off: rec.insn_off,
-                    type_id: rec.type_id,
-                });
-            }
-            secs.push(BtfExtSection::<BtfExtFunc> {
-                name: Btf::get_btf_str(strs, sec_hdr.sec_name_off)?,
-                rec_sz: rec_sz as usize,
-                recs: recs,
-            });
-
-            data = &data[(sec_hdr.num_info * rec_sz) as usize..];
-        }
-        Ok(secs)
+    fn load_func_secs(&self, data: &'a [u8]) -> BtfResult<Vec<BtfExtSection<'a, BtfExtFunc>>> {
+        self.load_ext_secs(data, "func info", size_of::<btf_ext_func_info>(), |rec| {
+            let rec = rec.pread_with::<btf_ext_func_info>(0, self.endian)?;
+            Ok(BtfExtFunc {
+                insn_off: rec.insn_off,
+                type_id: rec.type_id,
+            })
+        })
     }
 
-    fn load_line_secs(
-        &self,
-        mut data: &'a [u8],
-        strs: &'a [u8],
-    ) -> BtfResult<Vec<BtfExtSection<'a, BtfExtLine<'a>>>> {
-        let rec_sz = data.pread_with::<u32>(0, self.endian)?;
-        if rec_sz < size_of::<btf_ext_line_info>() as u32 {
-            return btf_error(format!(
-                "Too small line info record size: {}, expect at least: {}",
-                rec_sz,
-                size_of::<btf_ext_line_info>()
-            ));
-        }
-        data = &data[size_of::<u32>()..];
-        let mut secs = Vec::new();
-        while !data.is_empty() {
-            let sec_hdr = data.pread_with::<btf_ext_info_sec>(0, self.endian)?;
-            data = &data[size_of::<btf_ext_info_sec>()..];
-
-            let mut recs = Vec::new();
-            for i in 0..sec_hdr.num_info {
-                let off = (i * rec_sz) as usize;
-                let rec = data.pread_with::<btf_ext_line_info>(off, self.endian)?;
-                recs.push(BtfExtLine {
-                    insn_off: rec.insn_off,
-                    file_name: Btf::get_btf_str(strs, rec.file_name_off)?,
-                    src_line: Btf::get_btf_str(strs, rec.line_off)?,
-                    line_num: rec.line_col >> 10,
-                    col_num: rec.line_col & 0x3ff,
-                });
-            }
-            secs.push(BtfExtSection::<BtfExtLine> {
-                name: Btf::get_btf_str(strs, sec_hdr.sec_name_off)?,
-                rec_sz: rec_sz as usize,
-                recs: recs,
-            });
-
-            data = &data[(sec_hdr.num_info * rec_sz) as usize..];
-        }
-        Ok(secs)
+    fn load_line_secs(&self, data: &'a [u8]) -> BtfResult<Vec<BtfExtSection<'a, BtfExtLine<'a>>>> {
+        self.load_ext_secs(data, "line info", size_of::<btf_ext_line_info>(), |rec| {
+            let rec = rec.pread_with::<btf_ext_line_info>(0, self.endian)?;
+            Ok(BtfExtLine {
+                insn_off: rec.insn_off,
+                file_name: self.strs.get(rec.file_name_off)?,
+                src_line: self.strs.get(rec.line_off)?,
+                line_num: rec.line_col >> 10,
+                col_num: rec.line_col & 0x3ff,
+            })
+        })
     }
 
     fn load_core_reloc_secs(
         &self,
-        mut data: &'a [u8],
-        strs: &'a [u8],
+        data: &'a [u8],
     ) -> BtfResult<Vec<BtfExtSection<'a, BtfExtCoreReloc<'a>>>> {
-        let rec_sz = data.pread_with::<u32>(0, self.endian)?;
-        if rec_sz < size_of::<btf_ext_core_reloc>() as u32 {
-            return btf_error(format!(
-                "Too small CO-RE reloc record size: {}, expect at least: {}",
-                rec_sz,
-                size_of::<btf_ext_core_reloc>()
-            ));
-        }
-        data = &data[size_of::<u32>()..];
-        let mut secs = Vec::new();
-        while !data.is_empty() {
-            let sec_hdr = data.pread_with::<btf_ext_info_sec>(0, self.endian)?;
-            data = &data[size_of::<btf_ext_info_sec>()..];
-
-            let mut recs = Vec::new();
-            for i in 0..sec_hdr.num_info {
-                let off = (i * rec_sz) as usize;
-                let rec = data.pread_with::<btf_ext_core_reloc>(off, self.endian)?;
+        self.load_ext_secs(
+            data,
+            "CO-RE reloc",
+            size_of::<btf_ext_core_reloc>(),
+            |rec| {
+                let rec = rec.pread_with::<btf_ext_core_reloc>(0, self.endian)?;
                 let kind = match rec.kind {
                     BTF_FIELD_BYTE_OFFSET => BtfCoreRelocKind::ByteOff,
                     BTF_FIELD_BYTE_SIZE => BtfCoreRelocKind::ByteSz,
@@ -1502,29 +2695,56 @@ impl<'a> Btf<'a> {
                         return btf_error(format!("Unknown BTF CO-RE reloc kind: {}", rec.kind));
                     }
                 };
-                let relo = {
-                    let access_spec_str = Btf::get_btf_str(strs, rec.access_spec_off)?;
-                    let access_spec = Btf::parse_reloc_access_spec(&access_spec_str)?;
-                    BtfExtCoreReloc {
-                        insn_off: rec.insn_off,
-                        type_id: rec.type_id,
-                        access_spec_str: access_spec_str,
-                        access_spec: access_spec,
-                        kind: kind,
-                    }
-                };
-                recs.push(relo);
-            }
-            secs.push(BtfExtSection::<BtfExtCoreReloc> {
-                name: Btf::get_btf_str(strs, sec_hdr.sec_name_off)?,
-                rec_sz: rec_sz as usize,
-                recs: recs,
-            });
+                let access_spec_str = self.strs.get(rec.access_spec_off)?;
+                Ok(BtfExtCoreReloc {
+                    insn_off: rec.insn_off,
+                    type_id: rec.type_id,
+                    access_spec_str,
+                    access_spec: Btf::parse_reloc_access_spec(access_spec_str)?,
+                    kind,
+                })
+            },
+        )
+    }
 
-            data = &data[(sec_hdr.num_info * rec_sz) as usize..];
+    /// Loads records of one .BTF.ext info kind (`info`, e.g., func info), grouped by ELF section.
+    /// Records can be bigger than `min_rec_sz` bytes known to this crate, extra bytes are ignored.
+    fn load_ext_secs<T>(
+        &self,
+        data: &'a [u8],
+        info: &'static str,
+        min_rec_sz: usize,
+        mut load_rec: impl FnMut(&'a [u8]) -> BtfResult<T>,
+    ) -> BtfResult<Vec<BtfExtSection<'a, T>>> {
+        let rec_sz = data.pread_with::<u32>(0, self.endian)? as usize;
+        if rec_sz < min_rec_sz {
+            return btf_error(format!(
+                "Too small {} record size: {}, expect at least: {}",
+                info, rec_sz, min_rec_sz
+            ));
+        }
+        let mut off = size_of::<u32>();
+        let mut secs = Vec::new();
+        while off < data.len() {
+            let sec_hdr = data.pread_with::<btf_ext_info_sec>(off, self.endian)?;
+            off += size_of::<btf_ext_info_sec>();
+            let recs_data = (sec_hdr.num_info as usize)
+                .checked_mul(rec_sz)
+                .and_then(|len| data.get(off..off.checked_add(len)?))
+                .ok_or(BtfError::TruncatedExtInfo { info, offset: off })?;
+            secs.push(BtfExtSection {
+                name: self.strs.get(sec_hdr.sec_name_off)?,
+                rec_sz,
+                recs: recs_data
+                    .chunks_exact(rec_sz)
+                    .map(&mut load_rec)
+                    .collect::<BtfResult<Vec<T>>>()?,
+            });
+            off += recs_data.len();
         }
         Ok(secs)
     }
+
     fn parse_reloc_access_spec(access_spec_str: &str) -> BtfResult<Vec<usize>> {
         let mut spec = Vec::new();
         for p in access_spec_str.split(':') {
@@ -1532,9 +2752,185 @@ impl<'a> Btf<'a> {
         }
         Ok(spec)
     }
+}
 
-    fn get_btf_str(strs: &[u8], off: u32) -> BtfResult<&str> {
-        let c_str = unsafe { CStr::from_ptr(&strs[off as usize] as *const u8 as *const c_char) };
-        Ok(c_str.to_str()?)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::BtfBuilder;
+
+    fn sample_bytes() -> Vec<u8> {
+        let mut b = BtfBuilder::new();
+        let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+        let ptr = b.add_ptr(int).unwrap();
+        let members = vec![
+            BtfMember {
+                name: "a",
+                type_id: int,
+                bit_offset: 0,
+                bit_size: 0,
+            },
+            BtfMember {
+                name: "p",
+                type_id: ptr,
+                bit_offset: 64,
+                bit_size: 0,
+            },
+        ];
+        b.add_struct("s", 16, members).unwrap();
+        b.build().to_bytes().unwrap()
+    }
+
+    fn type_strs(btf: &Btf) -> Vec<String> {
+        btf.types().iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn kind_from_str() {
+        let kinds = [
+            BtfKind::Void,
+            BtfKind::Int,
+            BtfKind::Ptr,
+            BtfKind::Array,
+            BtfKind::Struct,
+            BtfKind::Union,
+            BtfKind::Enum,
+            BtfKind::Fwd,
+            BtfKind::Typedef,
+            BtfKind::Volatile,
+            BtfKind::Const,
+            BtfKind::Restrict,
+            BtfKind::Func,
+            BtfKind::FuncProto,
+            BtfKind::Var,
+            BtfKind::Datasec,
+            BtfKind::Float,
+            BtfKind::DeclTag,
+            BtfKind::TypeTag,
+            BtfKind::Enum64,
+        ];
+        for kind in kinds {
+            assert_eq!(kind.keyword().parse::<BtfKind>().unwrap(), kind);
+        }
+        let aliases = [
+            ("f", BtfKind::Float),
+            ("decltag", BtfKind::DeclTag),
+            ("typetag", BtfKind::TypeTag),
+            ("e64", BtfKind::Enum64),
+        ];
+        for (s, kind) in aliases {
+            assert_eq!(s.parse::<BtfKind>().unwrap(), kind);
+        }
+        assert!("enum32".parse::<BtfKind>().is_err());
+    }
+
+    #[test]
+    fn owned_btf_frees_data_on_drop() {
+        let owned = Btf::from_owned_bytes(sample_bytes()).unwrap();
+        let expected = type_strs(owned.btf());
+        assert_eq!(expected.len(), 4);
+        let data = Arc::downgrade(&owned._data);
+        drop(owned);
+        assert!(data.upgrade().is_none());
+
+        let reloaded = Btf::from_owned_bytes(sample_bytes()).unwrap();
+        assert_eq!(type_strs(reloaded.btf()), expected);
+    }
+
+    #[test]
+    fn owned_btf_can_move_across_threads() {
+        let owned = Btf::from_owned_bytes(sample_bytes()).unwrap();
+        let names = std::thread::spawn(move || {
+            let btf = owned.btf();
+            (1..btf.type_cnt())
+                .map(|id| btf.full_name(id))
+                .collect::<Vec<_>>()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(names, ["int", "<anon ptr>", "struct s"]);
+    }
+
+    #[test]
+    fn align_of_struct_with_fwd_member() {
+        let mut b = BtfBuilder::new();
+        let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+        let fwd = b.add_fwd("opaque", BtfFwdKind::Struct).unwrap();
+        let members = vec![
+            BtfMember {
+                name: "a",
+                type_id: int,
+                bit_offset: 0,
+                bit_size: 0,
+            },
+            BtfMember {
+                name: "o",
+                type_id: fwd,
+                bit_offset: 40,
+                bit_size: 0,
+            },
+        ];
+        let id = b.add_struct("s", 8, members).unwrap();
+        let btf = b.build();
+        assert_eq!(btf.get_align_of(fwd), 1);
+        assert_eq!(btf.get_align_of(id), 4);
+        assert!(!btf.is_struct_packed(id));
+        // zero alignment can't come from get_align_of, but mustn't cause division by zero
+        match btf.type_by_id(id) {
+            BtfType::Struct(t) => assert!(!btf.is_packed_with(t, &[0, 0])),
+            t => panic!("unexpected type {}", t),
+        }
+    }
+
+    #[test]
+    fn set_ptr_sz_resets_alignments() {
+        let mut b = BtfBuilder::new();
+        let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+        let ptr = b.add_ptr(int).unwrap();
+        let mut btf = b.build();
+        assert_eq!(btf.get_align_of(ptr), 8);
+        btf.set_ptr_sz(4);
+        assert_eq!(btf.get_align_of(ptr), 4);
+    }
+
+    #[test]
+    fn type_categories() {
+        let mut b = BtfBuilder::new();
+        let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+        let values = vec![BtfEnumValue {
+            name: "A",
+            value: 0,
+        }];
+        let proto = b.add_func_proto(int, vec![]).unwrap();
+        let expected = [
+            (0, TypeCategory::Special),
+            (int, TypeCategory::Integer),
+            (b.add_enum("e", 4, values).unwrap(), TypeCategory::Integer),
+            (b.add_float("double", 8).unwrap(), TypeCategory::Float),
+            (b.add_ptr(int).unwrap(), TypeCategory::Pointer),
+            (b.add_array(int, int, 2).unwrap(), TypeCategory::Aggregate),
+            (
+                b.add_struct("s", 0, vec![]).unwrap(),
+                TypeCategory::Aggregate,
+            ),
+            (
+                b.add_fwd("u", BtfFwdKind::Union).unwrap(),
+                TypeCategory::Aggregate,
+            ),
+            (proto, TypeCategory::Function),
+            (
+                b.add_func("f", proto, BtfFuncKind::Global).unwrap(),
+                TypeCategory::Function,
+            ),
+            (b.add_typedef("t", int).unwrap(), TypeCategory::Alias),
+            (b.add_const(int).unwrap(), TypeCategory::Qualifier),
+            (b.add_volatile(int).unwrap(), TypeCategory::Qualifier),
+        ];
+        let btf = b.build();
+        for (id, category) in expected {
+            let t = btf.type_by_id(id);
+            assert_eq!(t.category(), category, "{}", t);
+        }
+        assert_eq!(TypeCategory::Aggregate.to_string(), "aggregate");
     }
 }
@@ -0,0 +1,114 @@
+use crate::types::*;
+use crate::{btf_error, BtfResult};
+
+fn invalid<T>(id: u32, msg: String) -> BtfResult<T> {
+    btf_error(format!("Invalid type [{}]: {}", id, msg))
+}
+
+impl<'a> Btf<'a> {
+    /// Checks structural rules the kernel and libbpf enforce on BTF, beyond what's needed to
+    /// parse it: referenced type IDs are in range, void is only at ID 0, struct members go in
+    /// order and fit within struct, enum values are named, data section vars don't overlap and
+    /// fit within section, and only the last func proto param can be void (vararg). Errors
+    /// name the offending type ID. Data sections of zero size are not checked, as compilers
+    /// leave them to be filled in by libbpf.
+    pub fn validate(&self) -> BtfResult<()> {
+        self.validate_type_ids(1)?;
+        for (id, t) in self.iter().skip(1) {
+            match t {
+                BtfType::Void => return invalid(id, "void type is only allowed at ID 0".into()),
+                BtfType::Struct(t) | BtfType::Union(t) => self.validate_composite(id, t)?,
+                BtfType::Enum(t) | BtfType::Enum64(t) => {
+                    if let Some(i) = t.values.iter().position(|v| v.name.is_empty()) {
+                        return invalid(id, format!("enum value #{} has no name", i));
+                    }
+                }
+                BtfType::FuncProto(t) => {
+                    for (i, p) in t.params.iter().enumerate() {
+                        if p.type_id == 0 && (i + 1 != t.params.len() || !p.name.is_empty()) {
+                            return invalid(id, format!("param #{} is void, but not vararg", i));
+                        }
+                    }
+                }
+                BtfType::Datasec(t) => self.validate_datasec(id, t)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_composite(&self, id: u32, t: &BtfComposite) -> BtfResult<()> {
+        let mut last_off = 0;
+        for (i, m) in t.members.iter().enumerate() {
+            if t.is_struct && m.bit_offset < last_off {
+                return invalid(
+                    id,
+                    format!(
+                        "member #{} '{}' at bit offset {} precedes previous member at {}",
+                        i, m.name, m.bit_offset, last_off
+                    ),
+                );
+            }
+            last_off = m.bit_offset;
+            let bits = if m.bit_size != 0 {
+                m.bit_size as u64
+            } else if let BtfType::Int(int) = self.type_by_id(m.type_id) {
+                (int.offset + int.bits) as u64
+            } else {
+                self.get_size_of(m.type_id)
+                    .or_else(|e| invalid(id, format!("member #{} '{}': {}", i, m.name, e)))?
+                    as u64
+                    * 8
+            };
+            if m.bit_offset as u64 + bits > t.sz as u64 * 8 {
+                return invalid(
+                    id,
+                    format!(
+                        "member #{} '{}' (bits {}..{}) exceeds size of {} bytes",
+                        i,
+                        m.name,
+                        m.bit_offset,
+                        m.bit_offset as u64 + bits,
+                        t.sz
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_datasec(&self, id: u32, t: &BtfDatasec) -> BtfResult<()> {
+        if t.sz == 0 {
+            return Ok(());
+        }
+        let mut last_end = 0u64;
+        for (i, v) in t.vars.iter().enumerate() {
+            match self.type_by_id(v.type_id) {
+                BtfType::Var(_) | BtfType::Func(_) => {}
+                vt => {
+                    return invalid(id, format!("var #{} is not a var or func: {}", i, vt));
+                }
+            }
+            if (v.offset as u64) < last_end {
+                return invalid(
+                    id,
+                    format!(
+                        "var #{} at offset {} overlaps previous var ending at {}",
+                        i, v.offset, last_end
+                    ),
+                );
+            }
+            last_end = v.offset as u64 + v.sz as u64;
+            if last_end > t.sz as u64 {
+                return invalid(
+                    id,
+                    format!(
+                        "var #{} (bytes {}..{}) exceeds section size of {} bytes",
+                        i, v.offset, last_end, t.sz
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+}
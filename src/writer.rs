@@ -0,0 +1,245 @@
+use std::mem::size_of;
+
+use scroll::IOwrite;
+
+use crate::strings::StringTableBuilder;
+use crate::types::*;
+use crate::{btf_error, BtfResult};
+
+const MAX_VLEN: usize = 0xffff;
+/// max bit offset of a member of struct/union with bitfields, which shares 32 bits with size
+const MAX_BITFIELD_MEMBER_OFFSET: u32 = (1 << 24) - 1;
+
+fn info(kind: u32, vlen: usize, kflag: bool) -> BtfResult<u32> {
+    if vlen > MAX_VLEN {
+        return btf_error(format!(
+            "Too many members/params/values ({}) to encode, max is {}",
+            vlen, MAX_VLEN
+        ));
+    }
+    Ok((kflag as u32) << 31 | kind << 24 | vlen as u32)
+}
+
+impl<'a> Btf<'a> {
+    /// Encodes types back into raw BTF data (header, types, and strings), as expected by the
+    /// kernel and `load_from_bytes()`. String section is rebuilt from scratch, so each distinct
+    /// string is stored once. .BTF.ext data is not encoded.
+    pub fn to_bytes(&self) -> BtfResult<Vec<u8>> {
+        self.to_bytes_with_endian(self.endian())
+    }
+
+    /// Same as `to_bytes()`, but encodes BTF in given byte order, which doesn't have to match
+    /// the one BTF was loaded in, e.g., to produce big-endian BTF for s390x on x86-64.
+    pub fn to_bytes_with_endian(&self, endian: scroll::Endian) -> BtfResult<Vec<u8>> {
+        if self.base_type_cnt() != 0 {
+            return btf_error("Encoding split BTF is not supported".to_owned());
+        }
+        let mut strs = StringTableBuilder::new();
+        let mut types = Vec::new();
+        for t in &self.types()[1..] {
+            self.write_type(&mut types, t, &mut strs, endian)?;
+        }
+        let strs = strs.into_bytes();
+
+        let hdr = btf_header {
+            magic: BTF_MAGIC,
+            version: BTF_VERSION,
+            flags: self.flags(),
+            hdr_len: size_of::<btf_header>() as u32,
+            type_off: 0,
+            type_len: types.len() as u32,
+            str_off: types.len() as u32,
+            str_len: strs.len() as u32,
+        };
+        let mut data = Vec::with_capacity(size_of::<btf_header>() + types.len() + strs.len());
+        data.iowrite_with(hdr, endian)?;
+        data.extend_from_slice(&types);
+        data.extend_from_slice(&strs);
+        Ok(data)
+    }
+
+    fn write_type(
+        &self,
+        buf: &mut Vec<u8>,
+        t: &BtfType,
+        strs: &mut StringTableBuilder,
+        endian: scroll::Endian,
+    ) -> BtfResult<()> {
+        let name_off = strs.intern(t.name());
+        let (kind, vlen, kflag, type_id) = match t {
+            BtfType::Void => return btf_error("Void type can't be encoded".to_owned()),
+            BtfType::Int(t) => (BTF_KIND_INT, 0, false, t.sz),
+            BtfType::Ptr(t) => (BTF_KIND_PTR, 0, false, t.type_id),
+            BtfType::Array(_) => (BTF_KIND_ARRAY, 0, false, 0),
+            BtfType::Struct(t) | BtfType::Union(t) => (
+                if t.is_struct {
+                    BTF_KIND_STRUCT
+                } else {
+                    BTF_KIND_UNION
+                },
+                t.members.len(),
+                t.members.iter().any(|m| m.bit_size != 0),
+                t.sz,
+            ),
+            BtfType::Enum(t) => (BTF_KIND_ENUM, t.values.len(), t.is_signed, t.sz),
+            BtfType::Enum64(t) => (BTF_KIND_ENUM64, t.values.len(), t.is_signed, t.sz),
+            BtfType::Fwd(t) => (BTF_KIND_FWD, 0, t.kind == BtfFwdKind::Union, 0),
+            BtfType::Typedef(t) => (BTF_KIND_TYPEDEF, 0, false, t.type_id),
+            BtfType::Volatile(t) => (BTF_KIND_VOLATILE, 0, false, t.type_id),
+            BtfType::Const(t) => (BTF_KIND_CONST, 0, false, t.type_id),
+            BtfType::Restrict(t) => (BTF_KIND_RESTRICT, 0, false, t.type_id),
+            BtfType::Func(t) => {
+                let linkage = match t.kind {
+                    BtfFuncKind::Static => BTF_FUNC_STATIC,
+                    BtfFuncKind::Global => BTF_FUNC_GLOBAL,
+                    BtfFuncKind::Extern => BTF_FUNC_EXTERN,
+                    BtfFuncKind::Unknown => {
+                        return btf_error(format!("Func '{}' has unknown linkage", t.name))
+                    }
+                };
+                (BTF_KIND_FUNC, linkage as usize, false, t.proto_type_id)
+            }
+            BtfType::FuncProto(t) => (BTF_KIND_FUNC_PROTO, t.params.len(), false, t.res_type_id),
+            BtfType::Var(t) => (BTF_KIND_VAR, 0, false, t.type_id),
+            BtfType::Datasec(t) => (BTF_KIND_DATASEC, t.vars.len(), false, t.sz),
+            BtfType::Float(t) => (BTF_KIND_FLOAT, 0, false, t.sz),
+            BtfType::DeclTag(t) => (BTF_KIND_DECL_TAG, 0, false, t.type_id),
+            BtfType::TypeTag(t) => (BTF_KIND_TYPE_TAG, 0, false, t.type_id),
+        };
+        buf.iowrite_with(
+            btf_type {
+                name_off,
+                info: info(kind, vlen, kflag)?,
+                type_id,
+            },
+            endian,
+        )?;
+
+        match t {
+            BtfType::Int(t) => {
+                let enc = match t.encoding {
+                    BtfIntEncoding::None => 0,
+                    BtfIntEncoding::Signed => BTF_INT_SIGNED,
+                    BtfIntEncoding::Char => BTF_INT_CHAR,
+                    BtfIntEncoding::Bool => BTF_INT_BOOL,
+                };
+                buf.iowrite_with(enc << 24 | t.offset << 16 | t.bits, endian)?;
+            }
+            BtfType::Array(t) => buf.iowrite_with(
+                btf_array {
+                    val_type_id: t.val_type_id,
+                    idx_type_id: t.idx_type_id,
+                    nelems: t.nelems,
+                },
+                endian,
+            )?,
+            BtfType::Struct(t) | BtfType::Union(t) => {
+                let bits = t.members.iter().any(|m| m.bit_size != 0);
+                for m in &t.members {
+                    if bits && m.bit_offset > MAX_BITFIELD_MEMBER_OFFSET {
+                        return btf_error(format!(
+                            "Member '{}' of {} '{}' is at bit offset {}, max encodable in \
+                             struct/union with bitfields is {}",
+                            m.name,
+                            if t.is_struct { "struct" } else { "union" },
+                            t.name,
+                            m.bit_offset,
+                            MAX_BITFIELD_MEMBER_OFFSET
+                        ));
+                    }
+                    buf.iowrite_with(
+                        btf_member {
+                            name_off: strs.intern(m.name),
+                            type_id: m.type_id,
+                            offset: if bits {
+                                (m.bit_size as u32) << 24 | m.bit_offset
+                            } else {
+                                m.bit_offset
+                            },
+                        },
+                        endian,
+                    )?;
+                }
+            }
+            BtfType::Enum(t) => {
+                for v in &t.values {
+                    buf.iowrite_with(
+                        btf_enum {
+                            name_off: strs.intern(v.name),
+                            val: v.value as i32,
+                        },
+                        endian,
+                    )?;
+                }
+            }
+            BtfType::Enum64(t) => {
+                for v in &t.values {
+                    buf.iowrite_with(strs.intern(v.name), endian)?;
+                    buf.iowrite_with(v.value as u32, endian)?;
+                    buf.iowrite_with((v.value as u64 >> 32) as u32, endian)?;
+                }
+            }
+            BtfType::FuncProto(t) => {
+                for p in &t.params {
+                    buf.iowrite_with(
+                        btf_param {
+                            name_off: strs.intern(p.name),
+                            type_id: p.type_id,
+                        },
+                        endian,
+                    )?;
+                }
+            }
+            BtfType::Var(t) => {
+                let linkage = match t.kind {
+                    BtfVarKind::Static => BTF_VAR_STATIC,
+                    BtfVarKind::GlobalAlloc => BTF_VAR_GLOBAL_ALLOCATED,
+                    BtfVarKind::GlobalExtern => BTF_VAR_GLOBAL_EXTERNAL,
+                };
+                buf.iowrite_with(linkage, endian)?;
+            }
+            BtfType::Datasec(t) => {
+                for v in &t.vars {
+                    buf.iowrite_with(
+                        btf_datasec_var {
+                            type_id: v.type_id,
+                            offset: v.offset,
+                            size: v.sz,
+                        },
+                        endian,
+                    )?;
+                }
+            }
+            BtfType::DeclTag(t) => buf.iowrite_with(t.comp_idx, endian)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::BtfBuilder;
+
+    #[test]
+    fn bitfield_member_offset_overflow() {
+        let mut b = BtfBuilder::new();
+        let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+        let arr = b.add_array(int, int, 1 << 20).unwrap();
+        let member = |name, type_id, bit_offset, bit_size| BtfMember {
+            name,
+            type_id,
+            bit_offset,
+            bit_size,
+        };
+        let members = vec![
+            member("flag", int, 0, 1),
+            member("big", arr, 32, 0),
+            member("after", int, 32 + (1 << 25), 0),
+        ];
+        b.add_struct("s", 4 + (1 << 22) + 4, members).unwrap();
+        let err = b.build().to_bytes().unwrap_err();
+        assert!(err.to_string().contains("Member 'after' of struct 's'"));
+    }
+}
@@ -0,0 +1,796 @@
+mod common;
+
+use btf::builder::BtfBuilder;
+use btf::c_dumper::{CDumper, CDumperCfg, CTarget, Indent, SkipReason, SkippedType};
+use btf::types::*;
+
+/// Dumps all types matching filter, without prologue and epilogue
+fn dump_filtered<'a>(
+    btf: &'a Btf<'a>,
+    filter: Box<dyn Fn(u32, &'a BtfType<'a>) -> bool>,
+) -> String {
+    let mut dumper = CDumper::new(btf, Vec::new(), CDumperCfg::default());
+    dumper.dump_types(filter).unwrap();
+    String::from_utf8(dumper.into_writer()).unwrap()
+}
+
+#[test]
+fn name_regex_filter() {
+    let owned = common::load_sample("padding.o");
+    let btf = owned.btf();
+    let names = |re| -> Vec<&str> {
+        let filter = CDumper::filter_by_name_regex(re).unwrap();
+        (1..btf.type_cnt())
+            .map(|id| (id, btf.type_by_id(id)))
+            .filter(|&(id, t)| filter(id, t))
+            .map(|(_, t)| t.name())
+            .collect()
+    };
+    // literal patterns match anywhere in a name, with or without regex support
+    assert_eq!(names("pad"), ["pad1", "pad2", "pad3", "nopad"]);
+    #[cfg(not(feature = "no-regex"))]
+    assert_eq!(names("^pad[12]$"), ["pad1", "pad2"]);
+    #[cfg(feature = "no-regex")]
+    assert!(CDumper::filter_by_name_regex("^pad[12]$").is_err());
+}
+
+#[test]
+fn kind_filter_from_str() {
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let float = b.add_float("double", 8).unwrap();
+    let value = |name, value| BtfEnumValue { name, value };
+    b.add_enum("wide", 8, vec![value("W1", 1 << 40)]).unwrap();
+    b.add_enum("narrow", 4, vec![value("N1", 1)]).unwrap();
+    let tag = b
+        .add(BtfType::TypeTag(BtfTypeTag {
+            name: "user",
+            type_id: int,
+        }))
+        .unwrap();
+    let ptr = b.add_ptr(tag).unwrap();
+    b.add_typedef("uptr_t", ptr).unwrap();
+    b.add_typedef("real_t", float).unwrap();
+    let btf = b.build();
+
+    let filter = |s: &str| CDumper::<Vec<u8>>::filter_by_kinds(&[s.parse().unwrap()]);
+    assert_eq!(
+        dump_filtered(&btf, filter("enum64")),
+        "enum wide {\n\tW1 = 1099511627776ULL,\n};\n"
+    );
+    assert_eq!(
+        dump_filtered(&btf, filter("e64")),
+        dump_filtered(&btf, filter("enum64"))
+    );
+    for s in ["float", "f", "type_tag", "typetag"] {
+        let kind: BtfKind = s.parse().unwrap();
+        let f = filter(s);
+        let matched: Vec<u32> = btf
+            .iter()
+            .filter(|&(id, t)| f(id, t))
+            .map(|(id, _)| id)
+            .collect();
+        let expected: Vec<u32> = btf.iter_kind(kind).map(|(id, _)| id).collect();
+        assert_eq!(matched, expected, "{}", s);
+        assert_eq!(matched.len(), 1, "{}", s);
+    }
+}
+
+/// Checks C dump of types of `<name>.o` sample against `<name>.h`, returning the dump.
+fn check_sample(name: &str) -> String {
+    let owned = common::load_sample(&format!("{}.o", name));
+    let out = common::dump_c(owned.btf(), Default::default());
+    let expected = std::fs::read_to_string(common::sample_path(&format!("{}.h", name))).unwrap();
+    assert_eq!(out, expected);
+    out
+}
+
+#[test]
+fn golden_output() {
+    let owned = common::load_sample("golden.o");
+    let expected = std::fs::read_to_string(common::sample_path("golden.h")).unwrap();
+    let out = CDumper::dump_to_string(owned.btf(), Box::new(|_, _| true), CDumperCfg::default());
+    assert_eq!(out.unwrap(), expected);
+}
+
+/// golden_vmlinux.h is laid out the way `bpftool btf dump file golden.o format c` lays out
+/// vmlinux.h: type definitions wrapped in include guard and `preserve_access_index` pragmas
+#[test]
+fn golden_vmlinux_output() {
+    let owned = common::load_sample("golden.o");
+    let expected = std::fs::read_to_string(common::sample_path("golden_vmlinux.h")).unwrap();
+    let out = CDumper::dump_to_string(
+        owned.btf(),
+        Box::new(|_, _| true),
+        CDumperCfg::vmlinux_preset(),
+    );
+    assert_eq!(out.unwrap(), expected);
+}
+
+#[test]
+fn reset_starts_independent_output() {
+    let owned = common::load_sample("golden.o");
+    let mut dumper = CDumper::new(owned.btf(), Vec::new(), CDumperCfg::default());
+    dumper.dump_types(Box::new(|_, _| true)).unwrap();
+    dumper.reset();
+    dumper.dump_types(Box::new(|_, _| true)).unwrap();
+    let out = String::from_utf8(dumper.into_writer()).unwrap();
+    let expected = std::fs::read_to_string(common::sample_path("golden.h")).unwrap();
+    assert_eq!(out, expected.repeat(2));
+}
+
+#[test]
+fn skipped_report() {
+    let owned = common::load_sample("golden.o");
+    let btf = owned.btf();
+    let state = btf.id_by_name(Some(BtfKind::Enum), "state").unwrap();
+    let var = btf.id_by_name(Some(BtfKind::Var), "golden_root").unwrap();
+    let mut dumper = CDumper::new(btf, Vec::new(), CDumperCfg::default());
+    dumper.set_name_blacklist(&["state"]).unwrap();
+    // second pass re-requests the same types, which are reported only once
+    for _ in 0..2 {
+        dumper.dump_types(Box::new(|_, _| true)).unwrap();
+    }
+    let skipped = dumper.skipped().to_vec();
+    assert!(skipped.contains(&SkippedType {
+        id: state,
+        reason: SkipReason::Blacklisted
+    }));
+    assert!(skipped.contains(&SkippedType {
+        id: var,
+        reason: SkipReason::UnsupportedKind
+    }));
+    for s in &skipped {
+        assert_eq!(skipped.iter().filter(|s2| s2.id == s.id).count(), 1);
+    }
+    let out = String::from_utf8(dumper.into_writer()).unwrap();
+    assert!(!out.contains("enum state {"));
+    assert!(out.contains("\tenum state state;\n"));
+}
+
+#[test]
+fn enum_value_signedness() {
+    let mut b = BtfBuilder::new();
+    let values = vec![
+        BtfEnumValue {
+            name: "U_LOW",
+            value: 1,
+        },
+        BtfEnumValue {
+            name: "U_HIGH",
+            value: 0x8000_0000,
+        },
+    ];
+    b.add_enum("u32_vals", 4, values).unwrap();
+    let values = vec![BtfEnumValue {
+        name: "S_MIN",
+        value: i64::MIN,
+    }];
+    b.add_enum("s64_vals", 8, values).unwrap();
+    // reload, so that unsigned 32-bit value comes back sign-extended, as it does from real BTF
+    let bytes = b.build().to_bytes().unwrap();
+    let btf = Btf::load_from_bytes(&bytes, 8, scroll::LE).unwrap();
+
+    let e = match btf.type_by_id(1) {
+        BtfType::Enum(e) => e,
+        t => panic!("unexpected type {}", t),
+    };
+    assert!(!e.is_signed);
+    assert_eq!(e.values[1].value, i32::MIN as i64);
+    assert_eq!(e.value_of(&e.values[1]), 0x8000_0000);
+
+    assert_eq!(
+        common::dump_c(&btf, Default::default()),
+        "enum u32_vals {\n\
+         \tU_LOW = 1,\n\
+         \tU_HIGH = 2147483648ULL,\n\
+         };\n\
+         \n\
+         enum s64_vals {\n\
+         \tS_MIN = (-9223372036854775807LL - 1),\n\
+         };\n"
+    );
+
+    let mut raw = Vec::new();
+    btf.dump_raw(&mut raw).unwrap();
+    let raw = String::from_utf8(raw).unwrap();
+    assert!(raw.contains("'U_HIGH' val=2147483648\n"), "{}", raw);
+    assert!(raw.contains("'S_MIN' val=-9223372036854775808"), "{}", raw);
+    let json = btf.to_json();
+    assert!(json.contains("2147483648"), "{}", json);
+    assert!(!json.contains("-2147483648"), "{}", json);
+}
+
+#[test]
+fn unrepresentable_int_error() {
+    let mut b = BtfBuilder::new();
+    let long = b.add_int("long int", 64, BtfIntEncoding::Signed).unwrap();
+    let wide = b.add_int("__int128", 128, BtfIntEncoding::Signed).unwrap();
+    let members = vec![
+        BtfMember {
+            name: "x",
+            type_id: long,
+            bit_offset: 0,
+            bit_size: 0,
+        },
+        BtfMember {
+            name: "v",
+            type_id: wide,
+            bit_offset: 128,
+            bit_size: 0,
+        },
+    ];
+    b.add_struct("s", 32, members).unwrap();
+    let btf = b.build();
+    let cfg = || CDumperCfg {
+        target: CTarget {
+            has_int128: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    // #error has to precede the whole definition, not to be stuck in the middle of it
+    assert_eq!(
+        common::dump_c(&btf, cfg()),
+        "#error \"cannot represent type __int128: 128-bit integers are not supported by target\"\n\
+         struct s {\n\
+         \tlong int x;\n\
+         \tlong: 64;\n\
+         \t__int128 v;\n\
+         };\n"
+    );
+
+    let mut dumper = CDumper::new(
+        &btf,
+        Vec::new(),
+        CDumperCfg {
+            strict: true,
+            ..cfg()
+        },
+    );
+    assert!(dumper.dump_types(Box::new(|_, _| true)).is_err());
+}
+
+#[test]
+fn odd_width_int_bitfields() {
+    let mut b = BtfBuilder::new();
+    let int24 = b.add_int("int24", 24, BtfIntEncoding::Signed).unwrap();
+    let u24 = b.add_int("u24", 24, BtfIntEncoding::None).unwrap();
+    let u8_3 = b.add_int("unsigned char", 3, BtfIntEncoding::None).unwrap();
+    let members = vec![
+        BtfMember {
+            name: "a",
+            type_id: int24,
+            bit_offset: 0,
+            bit_size: 0,
+        },
+        BtfMember {
+            name: "b",
+            type_id: u24,
+            bit_offset: 32,
+            bit_size: 0,
+        },
+        BtfMember {
+            name: "c",
+            type_id: u8_3,
+            bit_offset: 64,
+            bit_size: 0,
+        },
+    ];
+    b.add_struct("s", 12, members).unwrap();
+    let btf = b.build();
+
+    // ints of non-standard width become bitfields of a C type of the same size and signedness
+    let out = common::dump_c(&btf, Default::default());
+    assert_eq!(
+        out,
+        "struct s {\n\
+         \tint a: 24;\n\
+         \tchar: 8;\n\
+         \tunsigned int b: 24;\n\
+         \tchar: 8;\n\
+         \tunsigned char c: 3;\n\
+         };\n"
+    );
+    // bitfields represent such ints exactly, so they are fine in strict mode, too
+    let strict = || CDumperCfg {
+        strict: true,
+        ..Default::default()
+    };
+    assert_eq!(common::dump_c(&btf, strict()), out);
+
+    // elsewhere they are referenced as C type of the same size, which is wider, though
+    let mut b = BtfBuilder::new();
+    let u24 = b.add_int("u24", 24, BtfIntEncoding::None).unwrap();
+    let my24 = b.add_typedef("my24", u24).unwrap();
+    let ptr = b.add_ptr(my24).unwrap();
+    let members = vec![
+        BtfMember {
+            name: "p",
+            type_id: ptr,
+            bit_offset: 0,
+            bit_size: 0,
+        },
+        BtfMember {
+            name: "t",
+            type_id: my24,
+            bit_offset: 64,
+            bit_size: 0,
+        },
+    ];
+    b.add_struct("s", 16, members).unwrap();
+    let btf = b.build();
+    assert_eq!(
+        common::dump_c(&btf, Default::default()),
+        "typedef unsigned int my24;\n\
+         \n\
+         struct s {\n\
+         \tmy24 *p;\n\
+         \tmy24 t;\n\
+         };\n"
+    );
+    let mut dumper = CDumper::new(&btf, Vec::new(), strict());
+    assert!(dumper.dump_types(Box::new(|_, _| true)).is_err());
+}
+
+#[test]
+fn type_closure_forward_declares_pointees() {
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let member = |name, type_id, bit_offset| BtfMember {
+        name,
+        type_id,
+        bit_offset,
+        bit_size: 0,
+    };
+    let pointee = b
+        .add_struct("pointee", 4, vec![member("x", int, 0)])
+        .unwrap();
+    let embedded = b
+        .add_struct("embedded", 4, vec![member("y", int, 0)])
+        .unwrap();
+    b.add_struct("unrelated", 4, vec![member("z", int, 0)])
+        .unwrap();
+    let ptr = b.add_ptr(pointee).unwrap();
+    let members = vec![member("p", ptr, 0), member("e", embedded, 64)];
+    let root = b.add_struct("root", 16, members).unwrap();
+    let btf = b.build();
+
+    let mut dumper = CDumper::new(&btf, Vec::new(), CDumperCfg::default());
+    dumper.dump_type_closure(root).unwrap();
+    assert_eq!(
+        String::from_utf8(dumper.into_writer()).unwrap(),
+        "struct embedded {\n\
+         \tint y;\n\
+         };\n\
+         \n\
+         struct pointee;\n\
+         \n\
+         struct root {\n\
+         \tstruct pointee *p;\n\
+         \tstruct embedded e;\n\
+         };\n"
+    );
+}
+
+#[test]
+fn deep_nesting_indent() {
+    let owned = common::load_sample("deep_nesting.o");
+    let btf = owned.btf();
+    let out = common::dump_c(btf, Default::default());
+    // output matches the source, which is indented with tabs as well
+    let src = std::fs::read_to_string(common::sample_path("deep_nesting.c")).unwrap();
+    let start = src.find("struct deep {").unwrap();
+    let end = src.find("};\n").unwrap() + 3;
+    assert_eq!(out, src[start..end]);
+
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[15], format!("{}struct {{", "\t".repeat(15)));
+    assert_eq!(lines[16], format!("{}int leaf;", "\t".repeat(16)));
+    assert_eq!(lines[17], format!("{}}} l15;", "\t".repeat(15)));
+
+    let cfg = CDumperCfg {
+        indent: Indent::Spaces(4),
+        ..Default::default()
+    };
+    let out = common::dump_c(btf, cfg);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[16], format!("{}int leaf;", " ".repeat(64)));
+    assert_eq!(lines[17], format!("{}}} l15;", " ".repeat(60)));
+}
+
+#[test]
+fn type_tag_modifiers() {
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let tag = |type_id| {
+        BtfType::TypeTag(BtfTypeTag {
+            name: "user",
+            type_id,
+        })
+    };
+    // ptr -> const -> type_tag -> int: const applies to tagged int, so it has to follow the tag
+    let tagged = b.add(tag(int)).unwrap();
+    let const_tagged = b.add_const(tagged).unwrap();
+    let a = b.add_ptr(const_tagged).unwrap();
+    // ptr -> type_tag -> const -> int, as emitted by clang for `const int __user *`
+    let const_int = b.add_const(int).unwrap();
+    let tagged_const = b.add(tag(const_int)).unwrap();
+    let b_ptr = b.add_ptr(tagged_const).unwrap();
+    let members = vec![
+        BtfMember {
+            name: "a",
+            type_id: a,
+            bit_offset: 0,
+            bit_size: 0,
+        },
+        BtfMember {
+            name: "b",
+            type_id: b_ptr,
+            bit_offset: 64,
+            bit_size: 0,
+        },
+    ];
+    b.add_struct("tags", 16, members).unwrap();
+    let btf = b.build();
+    let id = btf.id_by_name(Some(BtfKind::Struct), "tags").unwrap();
+
+    assert_eq!(
+        dump_filtered(&btf, Box::new(move |type_id, _| type_id == id)),
+        "struct tags {\n\
+         \tint __attribute__((btf_type_tag(\"user\"))) const *a;\n\
+         \tconst int __attribute__((btf_type_tag(\"user\"))) *b;\n\
+         };\n"
+    );
+}
+
+#[test]
+fn anon_members() {
+    let out = check_sample("anon_members");
+    // anonymous members have no declarator, nothing but `;` follows their closing brace
+    assert!(!out.contains(" ;"), "{}", out);
+    assert!(out.lines().all(|l| l == l.trim_end()), "{}", out);
+    assert_eq!(out.lines().filter(|&l| l == "\t};").count(), 3);
+    assert_eq!(out.lines().filter(|&l| l == "\t\t};").count(), 2);
+}
+
+#[test]
+fn func_ptr_ret_ptr() {
+    let out = check_sample("func_ptr_ret_ptr");
+    // pointer to func returning pointer, and pointer to func returning pointer to func
+    assert!(out.contains("\n\tint *(*a)();\n"), "{}", out);
+    assert!(out.contains("\n\tint (*(*b)())();\n"), "{}", out);
+    assert!(
+        out.contains("\n\tconst long int *(*(*c)(int))(long int);\n"),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn func_proto_no_args() {
+    let out = check_sample("func_proto_no_args");
+    assert!(
+        out.contains("typedef int (*varargs_fn)(int, ...);"),
+        "{}",
+        out
+    );
+
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let void_param = || BtfFuncParam {
+        name: "",
+        type_id: 0,
+    };
+    let int_param = BtfFuncParam {
+        name: "x",
+        type_id: int,
+    };
+    // zero params (GCC) and single void param (Clang) both mean no args
+    let zero = b.add_func_proto(int, vec![]).unwrap();
+    let void = b.add_func_proto(int, vec![void_param()]).unwrap();
+    let varargs = b
+        .add_func_proto(int, vec![int_param, void_param()])
+        .unwrap();
+    let btf = b.build();
+    let mut dumper = CDumper::new(&btf, Vec::new(), CDumperCfg::default());
+    assert_eq!(dumper.type_decl_string(zero, "f").unwrap(), "int f()");
+    assert_eq!(dumper.type_decl_string(void, "f").unwrap(), "int f()");
+    assert_eq!(
+        dumper.type_decl_string(varargs, "f").unwrap(),
+        "int f(int x, ...)"
+    );
+}
+
+#[test]
+fn func_proto_const_ret() {
+    // GCC drops qualifiers of return types, so the sample only checks they don't break anything
+    check_sample("func_proto_const_ret");
+
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let const_int = b.add_const(int).unwrap();
+    let ptr_const_int = b.add_ptr(const_int).unwrap();
+    let ptr_int = b.add_ptr(int).unwrap();
+    let const_ptr_int = b.add_const(ptr_int).unwrap();
+    let mut fn_ptrs = Vec::new();
+    for (name, res) in [
+        ("const_ret_fn", const_int),
+        ("ptr_to_const_ret_fn", ptr_const_int),
+        ("const_ptr_ret_fn", const_ptr_int),
+    ] {
+        let proto = b.add_func_proto(res, vec![]).unwrap();
+        let ptr = b.add_ptr(proto).unwrap();
+        b.add_typedef(name, ptr).unwrap();
+        fn_ptrs.push(ptr);
+    }
+    let btf = b.build();
+
+    let typedefs = dump_filtered(&btf, Box::new(|_, t| t.kind() == BtfKind::Typedef));
+    assert_eq!(
+        typedefs,
+        "typedef const int (*const_ret_fn)();\n\
+         \n\
+         typedef const int *(*ptr_to_const_ret_fn)();\n\
+         \n\
+         typedef int * const (*const_ptr_ret_fn)();\n"
+    );
+    let mut dumper = CDumper::new(&btf, Vec::new(), CDumperCfg::default());
+    let decls: Vec<String> = fn_ptrs
+        .iter()
+        .map(|&id| dumper.type_decl_string(id, "f").unwrap())
+        .collect();
+    assert_eq!(
+        decls,
+        [
+            "const int (*f)()",
+            "const int *(*f)()",
+            "int * const (*f)()"
+        ]
+    );
+}
+
+#[test]
+fn array_params() {
+    // array params decay to pointers, qualifiers in brackets qualify the pointer itself
+    let out = check_sample("array_params");
+    assert!(out.contains("(*arr_param_fn)(int *);"), "{}", out);
+    assert!(
+        out.contains("(*const_arr_param_fn)(int * const);"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("(*restrict_arr_param_fn)(int * restrict);"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("(*const_elem_arr_param_fn)(const int *);"),
+        "{}",
+        out
+    );
+
+    // genuine array params, which compilers don't produce, keep their form, but not modifiers
+    // of array itself
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let arr = b.add_array(int, int, 4).unwrap();
+    let const_arr = b.add_const(arr).unwrap();
+    let params = vec![
+        BtfFuncParam {
+            name: "a",
+            type_id: arr,
+        },
+        BtfFuncParam {
+            name: "b",
+            type_id: const_arr,
+        },
+    ];
+    let proto = b.add_func_proto(0, params).unwrap();
+    let btf = b.build();
+    let mut dumper = CDumper::new(&btf, Vec::new(), CDumperCfg::default());
+    assert_eq!(
+        dumper.type_decl_string(proto, "f").unwrap(),
+        "void f(int a[4], int b[4])"
+    );
+}
+
+#[test]
+fn name_anon_members() {
+    let owned = common::load_sample("anon_members.o");
+    let cfg = CDumperCfg {
+        name_anon_members: true,
+        ..Default::default()
+    };
+    let out = common::dump_c(owned.btf(), cfg);
+    let names: Vec<&str> = out
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix("} "))
+        .collect();
+    // names are unique within each enclosing struct/union
+    assert_eq!(
+        names,
+        [
+            "__anon_0;",
+            "__anon_0;",
+            "__anon_0;",
+            "__anon_1;",
+            "named;",
+            "__anon_0;"
+        ]
+    );
+
+    // generated names skip ones taken by actual fields
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let field = |name, type_id| BtfMember {
+        name,
+        type_id,
+        bit_offset: 0,
+        bit_size: 0,
+    };
+    let inner = b.add_union("", 4, vec![field("x", int)]).unwrap();
+    let members = vec![field("__anon_0", int), field("", inner), field("", inner)];
+    b.add_union("u", 4, members).unwrap();
+    let btf = b.build();
+    let cfg = CDumperCfg {
+        name_anon_members: true,
+        ..Default::default()
+    };
+    let out = common::dump_c(&btf, cfg);
+    assert!(out.contains("\tint __anon_0;\n"), "{}", out);
+    assert!(out.contains("\t} __anon_1;\n"), "{}", out);
+    assert!(out.contains("\t} __anon_2;\n"), "{}", out);
+}
+
+#[test]
+fn min_id_skips_base_types() {
+    let mut b = BtfBuilder::new();
+    let field = |name, type_id, bit_offset| BtfMember {
+        name,
+        type_id,
+        bit_offset,
+        bit_size: 0,
+    };
+    // "base" types
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let base = b.add_struct("base", 4, vec![field("a", int, 0)]).unwrap();
+    let base_td = b.add_typedef("base_t", base).unwrap();
+    // "program" types appended after them
+    let min_id = b.add_ptr(int).unwrap();
+    let members = vec![
+        field("b", base, 0),
+        field("t", base_td, 32),
+        field("p", min_id, 64),
+    ];
+    b.add_struct("prog", 16, members).unwrap();
+    let btf = b.build();
+
+    let cfg = CDumperCfg {
+        min_id,
+        ..Default::default()
+    };
+    assert_eq!(
+        common::dump_c(&btf, cfg),
+        "struct prog {\n\
+         \tstruct base b;\n\
+         \tbase_t t;\n\
+         \tint *p;\n\
+         };\n"
+    );
+}
+
+#[test]
+fn type_tag_transparent_for_layout() {
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let ptr = b.add_ptr(int).unwrap();
+    let tag = b
+        .add(BtfType::TypeTag(BtfTypeTag {
+            name: "percpu",
+            type_id: ptr,
+        }))
+        .unwrap();
+    let td = b.add_typedef("percpu_ptr", tag).unwrap();
+    let btf = b.build();
+
+    // sizing and alignment look through type tags...
+    for id in [tag, td] {
+        assert_eq!(btf.get_size_of(id).unwrap(), 8);
+        assert_eq!(btf.get_align_of(id), 8);
+    }
+    assert_eq!(btf.skip_mods(tag), ptr);
+    assert_eq!(btf.skip_mods_and_typedefs(td), ptr);
+
+    // ...while declarations keep them
+    let mut dumper = CDumper::new(&btf, Vec::new(), CDumperCfg::default());
+    assert_eq!(
+        dumper.type_decl_string(tag, "p").unwrap(),
+        "int * __attribute__((btf_type_tag(\"percpu\"))) p"
+    );
+}
+
+#[test]
+fn max_types_limit() {
+    let owned = common::load_sample("golden.o");
+    let btf = owned.btf();
+    // top-level definitions end with `;` at the start of a line or right after `}`
+    let def_cnt = |out: &str| {
+        out.lines()
+            .filter(|l| !l.starts_with(char::is_whitespace) && l.ends_with(';'))
+            .count()
+    };
+    let all = common::dump_c(btf, Default::default());
+    assert_eq!(def_cnt(&all), 5);
+
+    for n in 1..5 {
+        let cfg = CDumperCfg {
+            max_types: n,
+            ..Default::default()
+        };
+        let mut dumper = CDumper::new(btf, Vec::new(), cfg);
+        dumper.dump_types(Box::new(|_, _| true)).unwrap();
+        assert!(dumper.truncated());
+        let out = String::from_utf8(dumper.into_writer()).unwrap();
+        assert_eq!(def_cnt(&out), n, "{}", out);
+        // the first N definitions in order, cut at definition boundary
+        let note = format!("/* output truncated after {} type definitions */\n", n);
+        let defs = out.strip_suffix(&note).unwrap();
+        assert!(all.starts_with(defs), "{}", out);
+    }
+
+    let cfg = CDumperCfg {
+        max_types: 5,
+        ..Default::default()
+    };
+    let mut dumper = CDumper::new(btf, Vec::new(), cfg);
+    dumper.dump_types(Box::new(|_, _| true)).unwrap();
+    assert!(!dumper.truncated());
+    assert_eq!(String::from_utf8(dumper.into_writer()).unwrap(), all);
+}
+
+#[test]
+fn stable_names_independent_of_order() {
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let field = BtfMember {
+        name: "x",
+        type_id: int,
+        bit_offset: 0,
+        bit_size: 0,
+    };
+    let first = b.add_struct("dup", 4, vec![field.clone()]).unwrap();
+    let second = b.add_struct("dup", 4, vec![field]).unwrap();
+    let btf = b.build();
+
+    let names = |stable_names, order: &[u32]| {
+        let cfg = CDumperCfg {
+            stable_names,
+            ..Default::default()
+        };
+        let mut dumper = CDumper::new(&btf, Vec::new(), cfg);
+        for &id in order {
+            dumper.dump_type(id).unwrap();
+        }
+        let mut names: Vec<(u32, String)> = dumper
+            .name_map()
+            .iter()
+            .filter(|(&id, _)| id == first || id == second)
+            .map(|(&id, name)| (id, name.clone()))
+            .collect();
+        names.sort();
+        (names, String::from_utf8(dumper.into_writer()).unwrap())
+    };
+    let (in_order, out) = names(true, &[first, second]);
+    assert_eq!(
+        in_order,
+        [(first, "dup".to_owned()), (second, "dup___2".to_owned())]
+    );
+    assert!(out.contains("struct dup___2 {"), "{}", out);
+    assert_eq!(names(true, &[second, first]).0, in_order);
+    assert_eq!(names(true, &[second]).0, in_order);
+    // without upfront resolution, whichever type comes first gets the plain name
+    assert_ne!(names(false, &[second, first]).0, in_order);
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use btf::builder::BtfBuilder;
+use btf::types::*;
+
+#[test]
+fn canonical_text_is_stable_across_loads() {
+    let first = common::load_sample("golden.o");
+    let second = common::load_sample("golden.o");
+    let text = first.btf().to_canonical_text();
+    assert!(!text.is_empty());
+    assert_eq!(text, second.btf().to_canonical_text());
+}
+
+#[test]
+fn canonical_text_ignores_type_order() {
+    let field = |name, type_id| BtfMember {
+        name,
+        type_id,
+        bit_offset: 0,
+        bit_size: 0,
+    };
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let ptr = b.add_ptr(int).unwrap();
+    b.add_struct("a", 8, vec![field("p", ptr)]).unwrap();
+    b.add_typedef("t", int).unwrap();
+    let forward = b.build();
+
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    b.add_typedef("t", int).unwrap();
+    let ptr = b.add_ptr(int).unwrap();
+    b.add_struct("a", 8, vec![field("p", ptr)]).unwrap();
+    let reordered = b.build();
+
+    assert_ne!(forward.types(), reordered.types());
+    assert_eq!(forward.to_canonical_text(), reordered.to_canonical_text());
+}
@@ -0,0 +1,35 @@
+//! Helpers shared by integration tests. Sample objects in tests/samples are built from
+//! same-named C files with `gcc -gbtf -c`.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use btf::c_dumper::{CDumper, CDumperCfg};
+use btf::types::{Btf, OwnedBtf};
+
+pub fn sample_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/samples")
+        .join(name)
+}
+
+pub fn load_sample(name: &str) -> OwnedBtf<'static> {
+    Btf::from_path(&sample_path(name)).unwrap()
+}
+
+/// Path of a file in temp dir, unique to the test process.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("btfdump-{}-{}", std::process::id(), name))
+}
+
+/// C dump of all types, without prologue and epilogue.
+pub fn dump_c(btf: &Btf, cfg: CDumperCfg) -> String {
+    let mut dumper = CDumper::new(btf, Vec::new(), cfg);
+    dumper.dump_types(Box::new(|_, _| true)).unwrap();
+    String::from_utf8(dumper.into_writer()).unwrap()
+}
+
+/// Display output of all types, one per line.
+pub fn type_strs(btf: &Btf) -> Vec<String> {
+    btf.types().iter().map(|t| t.to_string()).collect()
+}
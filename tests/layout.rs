@@ -0,0 +1,173 @@
+mod common;
+
+use btf::layout::Hole;
+use btf::types::{BtfKind, BtfType};
+
+#[test]
+fn struct_layout_holes_and_padding() {
+    let owned = common::load_sample("padding.o");
+    let btf = owned.btf();
+    let id = btf.id_by_name(Some(BtfKind::Struct), "pad3").unwrap();
+    let layout = btf.struct_layout(id).unwrap();
+    assert_eq!((layout.sz, layout.align), (24, 8));
+    let members: Vec<(&str, u32, u32)> = layout
+        .members
+        .iter()
+        .map(|m| (m.name, m.bit_offset, m.bit_size))
+        .collect();
+    assert_eq!(members, [("a", 0, 32), ("b", 64, 64), ("c", 128, 32)]);
+    assert_eq!(
+        layout.holes,
+        [Hole {
+            bit_offset: 32,
+            bit_size: 32
+        }]
+    );
+    assert_eq!(layout.tail_padding_bits, 32);
+    assert_eq!(layout.padding_bits(), 64);
+
+    let nopad = btf.id_by_name(Some(BtfKind::Struct), "nopad").unwrap();
+    assert_eq!(btf.struct_layout(nopad).unwrap().padding_bits(), 0);
+    assert_eq!(btf.total_padding(), 4 + 4 + 8);
+}
+
+#[test]
+fn padding_breakdown() {
+    let owned = common::load_sample("padding.o");
+    let btf = owned.btf();
+    let id = |name| btf.id_by_name(Some(BtfKind::Struct), name).unwrap();
+    // worst offender first, ties broken by type ID; structs without padding are left out
+    let mut expected = vec![(id("pad1"), 4), (id("pad2"), 4)];
+    expected.sort();
+    expected.insert(0, (id("pad3"), 8));
+    assert_eq!(btf.padding_by_struct(), expected);
+    assert_eq!(btf.total_padding(), 16);
+}
+
+#[test]
+fn struct_layout_rejects_bad_ids() {
+    let owned = common::load_sample("padding.o");
+    let btf = owned.btf();
+    assert!(btf.struct_layout(btf.type_cnt()).is_err());
+    assert!(btf.struct_layout(u32::MAX).is_err());
+    let int = (1..btf.type_cnt())
+        .find(|&id| matches!(btf.type_by_id(id), BtfType::Int(_)))
+        .unwrap();
+    assert!(btf.struct_layout(int).is_err());
+}
+
+#[test]
+fn cacheline_straddle() {
+    let owned = common::load_sample("cacheline.o");
+    let btf = owned.btf();
+    let id = btf
+        .id_by_name(Some(BtfKind::Struct), "cacheline_test")
+        .unwrap();
+    let report = btf.cacheline_report(id, 64).unwrap();
+    let lines: Vec<(&str, u32, u32, bool)> = report
+        .iter()
+        .map(|m| (m.name, m.first_line, m.last_line, m.straddles()))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            ("a", 0, 0, false),
+            ("b", 0, 0, false),
+            ("straddling", 0, 1, true),
+            ("c", 1, 1, false),
+            ("d", 1, 1, false),
+        ]
+    );
+    // with 32-byte lines, long arrays cross line boundaries too
+    let report = btf.cacheline_report(id, 32).unwrap();
+    assert_eq!((report[0].first_line, report[0].last_line), (0, 1));
+    assert!(btf.cacheline_report(id, 0).is_err());
+}
+
+#[test]
+fn nested_packed_alignment() {
+    let owned = common::load_sample("packed_nested.o");
+    let btf = owned.btf();
+    let inner = btf.id_by_name(Some(BtfKind::Struct), "inner").unwrap();
+    let outer = btf.id_by_name(Some(BtfKind::Struct), "outer").unwrap();
+    assert!(btf.is_struct_packed(inner));
+    assert_eq!(btf.get_align_of(inner), 1);
+    // packed struct lowers alignment of what embeds it only as far as its own members go
+    assert!(!btf.is_struct_packed(outer));
+    assert_eq!(btf.get_align_of(outer), 8);
+
+    let layout = btf.struct_layout(outer).unwrap();
+    let offsets: Vec<(&str, u32)> = layout
+        .members
+        .iter()
+        .map(|m| (m.name, m.bit_offset / 8))
+        .collect();
+    assert_eq!(
+        offsets,
+        [("c", 0), ("in", 1), ("s", 6), ("arr", 8), ("l", 24)]
+    );
+    assert_eq!((layout.sz, layout.align), (32, 8));
+    // alignments are memoized, repeated queries have to agree with the first one
+    assert_eq!(btf.get_align_of(inner), 1);
+    assert_eq!(btf.get_align_of(outer), 8);
+}
+
+#[test]
+fn assert_layout_against_rust_mirror() {
+    #[repr(C)]
+    struct Pad3 {
+        a: i32,
+        b: i64,
+        c: i32,
+    }
+    #[repr(C, packed)]
+    struct Pad3Packed {
+        a: i32,
+        b: i64,
+        c: i32,
+    }
+
+    let owned = common::load_sample("padding.o");
+    let btf = owned.btf();
+    let id = btf.id_by_name(Some(BtfKind::Struct), "pad3").unwrap();
+    btf.assert_layout(id, &btf::expected_layout!(Pad3 { a, b, c }))
+        .unwrap();
+    // fields can be listed in any order
+    btf.assert_layout(id, &btf::expected_layout!(Pad3 { c, a, b }))
+        .unwrap();
+
+    let err = btf
+        .assert_layout(id, &btf::expected_layout!(Pad3Packed { a, b, c }))
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("size is 24, expected 16"),
+        "{}",
+        err
+    );
+
+    let mut expected = btf::expected_layout!(Pad3 { a, b, c });
+    expected.fields[1].1 = 4;
+    let err = btf.assert_layout(id, &expected).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("field 'b' is at offset 8, expected 4"),
+        "{}",
+        err
+    );
+
+    expected.fields.remove(1);
+    let err = btf.assert_layout(id, &expected).unwrap_err();
+    assert!(
+        err.to_string().contains("field 'b' is not expected"),
+        "{}",
+        err
+    );
+    let mut expected = btf::expected_layout!(Pad3 { a, b, c });
+    expected.fields.push(("d", 20));
+    let err = btf.assert_layout(id, &expected).unwrap_err();
+    assert!(
+        err.to_string().contains("expected field 'd' is missing"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,413 @@
+mod common;
+
+use std::convert::TryInto;
+use std::io::Write;
+
+use btf::builder::BtfBuilder;
+use btf::types::{Btf, BtfIntEncoding, BtfKind, BtfMember, BtfType};
+use btf::BtfError;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use object::{Object, ObjectSection};
+
+use common::{sample_path, temp_path, type_strs};
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(data).unwrap();
+    enc.finish().unwrap()
+}
+
+#[test]
+fn from_path_detects_format() {
+    let elf_path = sample_path("golden.o");
+    let elf = std::fs::read(&elf_path).unwrap();
+    let raw = object::File::parse(&*elf)
+        .unwrap()
+        .section_by_name(".BTF")
+        .unwrap()
+        .data()
+        .unwrap()
+        .to_vec();
+    let expected = type_strs(Btf::from_path(&elf_path).unwrap().btf());
+    assert!(expected.len() > 1);
+
+    for (name, data) in [
+        ("golden.btf", raw.clone()),
+        ("golden.o.gz", gzip(&elf)),
+        ("golden.btf.gz", gzip(&raw)),
+    ] {
+        let path = temp_path(name);
+        std::fs::write(&path, data).unwrap();
+        let btf = Btf::from_path(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(type_strs(btf.unwrap().btf()), expected, "{}", name);
+    }
+}
+
+#[test]
+fn from_path_rejects_unknown_format() {
+    let path = temp_path("garbage.bin");
+    std::fs::write(&path, b"definitely not BTF").unwrap();
+    let err = Btf::from_path(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert!(err.to_string().contains("Unrecognized BTF file format"));
+
+    let err = Btf::from_path(&temp_path("missing.o")).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<BtfError>(),
+        Some(BtfError::Io(_))
+    ));
+}
+
+#[test]
+fn big_endian_fixture() {
+    let owned = common::load_sample("big_endian.btf");
+    let btf = owned.btf();
+    assert_eq!(btf.endian(), scroll::BE);
+
+    let id = btf.id_by_name(Some(BtfKind::Enum), "signed_vals").unwrap();
+    let e = match btf.type_by_id(id) {
+        BtfType::Enum(e) => e,
+        t => panic!("unexpected type {}", t),
+    };
+    assert_eq!(e.sz, 4);
+    let values: Vec<(&str, i64)> = e.values.iter().map(|v| (v.name, v.value)).collect();
+    assert_eq!(values, [("SV_NEG", -2), ("SV_POS", 0x12345678)]);
+
+    let id = btf.id_by_name(Some(BtfKind::Struct), "flags").unwrap();
+    let s = match btf.type_by_id(id) {
+        BtfType::Struct(s) => s,
+        t => panic!("unexpected type {}", t),
+    };
+    let layout: Vec<(&str, u32, u8)> = s
+        .members
+        .iter()
+        .map(|m| (m.name, m.bit_offset, m.bit_size))
+        .collect();
+    assert_eq!(
+        layout[..4],
+        [("a", 0, 1), ("b", 1, 7), ("c", 8, 24), ("d", 64, 40)]
+    );
+
+    // int (*cb)(struct flags *f, long arg)
+    let cb = s.members.iter().find(|m| m.name == "cb").unwrap();
+    let proto_id = match btf.type_by_id(cb.type_id) {
+        BtfType::Ptr(p) => p.type_id,
+        t => panic!("unexpected type {}", t),
+    };
+    let proto = match btf.type_by_id(proto_id) {
+        BtfType::FuncProto(p) => p,
+        t => panic!("unexpected type {}", t),
+    };
+    assert_eq!(btf.full_name(proto.res_type_id), "int");
+    assert_eq!(proto.params.len(), 2);
+    match btf.type_by_id(proto.params[0].type_id) {
+        BtfType::Ptr(p) => assert_eq!(p.type_id, id),
+        t => panic!("unexpected type {}", t),
+    }
+    assert_eq!(btf.full_name(proto.params[1].type_id), "long int");
+
+    let expected = std::fs::read_to_string(sample_path("big_endian.h")).unwrap();
+    assert_eq!(common::dump_c(btf, Default::default()), expected);
+}
+
+/// Raw little-endian BTF of a single `int` type.
+fn int_btf() -> Vec<u8> {
+    let mut b = BtfBuilder::new();
+    b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    b.build().to_bytes().unwrap()
+}
+
+/// Offsets of `btf_header` fields corrupted by tests
+const HDR_VERSION: usize = 2;
+const HDR_TYPE_LEN: usize = 12;
+const HDR_STR_LEN: usize = 20;
+
+fn patch_u32(data: &mut [u8], off: usize, f: impl Fn(u32) -> u32) {
+    let v = u32::from_le_bytes(data[off..off + 4].try_into().unwrap());
+    data[off..off + 4].copy_from_slice(&f(v).to_le_bytes());
+}
+
+fn load_err(data: &[u8]) -> BtfError {
+    let err = Btf::load_from_bytes(data, 8, scroll::LE).unwrap_err();
+    match err.downcast::<BtfError>() {
+        Ok(err) => *err,
+        Err(err) => panic!("unexpected error {}", err),
+    }
+}
+
+#[test]
+fn raw_load_errors() {
+    let good = int_btf();
+    Btf::load_from_bytes(&good, 8, scroll::LE).unwrap();
+
+    let mut data = good.clone();
+    data[0] = 0;
+    assert!(matches!(load_err(&data), BtfError::InvalidMagic(_)));
+
+    let mut data = good.clone();
+    data[HDR_VERSION] = 2;
+    assert!(matches!(load_err(&data), BtfError::UnsupportedVersion(2)));
+
+    let mut data = good.clone();
+    patch_u32(&mut data, HDR_STR_LEN, |len| len + 1);
+    match load_err(&data) {
+        BtfError::SectionOutOfBounds {
+            section: "string",
+            end,
+            data_len,
+        } => {
+            assert_eq!(data_len, good.len());
+            assert_eq!(end, good.len() + 1);
+        }
+        err => panic!("unexpected error {}", err),
+    }
+
+    let mut data = good.clone();
+    patch_u32(&mut data, HDR_TYPE_LEN, |len| len + 0x1000);
+    assert!(matches!(
+        load_err(&data),
+        BtfError::SectionOutOfBounds {
+            section: "type",
+            ..
+        }
+    ));
+
+    // int is a 12-byte btf_type followed by 4-byte encoding, cut the latter short
+    let mut data = good.clone();
+    patch_u32(&mut data, HDR_TYPE_LEN, |len| len - 2);
+    assert!(matches!(
+        load_err(&data),
+        BtfError::TruncatedType { offset: 0 }
+    ));
+
+    // string section is "\0int\0", drop the NUL terminating "int"
+    let mut data = good.clone();
+    *data.last_mut().unwrap() = b'x';
+    assert!(matches!(load_err(&data), BtfError::UnterminatedString(1)));
+
+    let mut data = good;
+    data[24..28].copy_from_slice(&0x100u32.to_le_bytes());
+    assert!(matches!(load_err(&data), BtfError::BadStringOffset(0x100)));
+}
+
+/// multi_btf.o is golden.o with extra sections added by
+/// `objcopy --add-section .BTF.extra=<.BTF of small_enums.o> --add-section .BTF.empty=/dev/null
+/// --add-section .BTF.bad=<8 bytes of garbage>`
+#[test]
+fn load_all_sections_merges_sections() {
+    let data = std::fs::read(sample_path("multi_btf.o")).unwrap();
+    let elf = object::File::parse(&*data).unwrap();
+    let (btf, errs) = Btf::load_all_sections(&elf).unwrap();
+
+    let golden = common::load_sample("golden.o");
+    let extra = common::load_sample("small_enums.o");
+    let golden_cnt = golden.btf().type_cnt();
+    assert_eq!(btf.type_cnt(), golden_cnt + extra.btf().type_cnt() - 1);
+    // types of each section keep their order, with references remapped past preceding ones
+    let strs = type_strs(&btf);
+    assert_eq!(strs[..golden_cnt as usize], type_strs(golden.btf())[..]);
+    let id = btf
+        .id_by_name(Some(BtfKind::Struct), "small_enums")
+        .unwrap();
+    assert!(id >= golden_cnt);
+    let t = match btf.type_by_id(id) {
+        BtfType::Struct(t) => t,
+        t => panic!("unexpected type {}", t),
+    };
+    let member_types: Vec<String> = t.members.iter().map(|m| btf.full_name(m.type_id)).collect();
+    assert_eq!(
+        member_types[..3],
+        ["unsigned char", "enum byte_enum", "enum short_enum"]
+    );
+    match btf.type_by_id(t.members[3].type_id) {
+        BtfType::Array(a) => assert_eq!(btf.full_name(a.val_type_id), "enum byte_enum"),
+        t => panic!("unexpected type {}", t),
+    }
+
+    // zero-length section holds no blobs and isn't an error, garbage is reported and skipped
+    assert_eq!(
+        errs.len(),
+        1,
+        "{:?}",
+        errs.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+    );
+    assert_eq!(errs[0].section, ".BTF.bad");
+    assert_eq!(errs[0].offset, 0);
+}
+
+#[test]
+fn type_section_exactly_covered() {
+    let mut b = BtfBuilder::new();
+    let int = b.add_int("int", 32, BtfIntEncoding::Signed).unwrap();
+    let members = vec![
+        BtfMember {
+            name: "a",
+            type_id: int,
+            bit_offset: 0,
+            bit_size: 0,
+        },
+        BtfMember {
+            name: "b",
+            type_id: int,
+            bit_offset: 32,
+            bit_size: 0,
+        },
+    ];
+    b.add_struct("s", 8, members).unwrap();
+    let good = b.build().to_bytes().unwrap();
+    // int takes 16 bytes, struct with two members 12 + 2 * 12 bytes
+    let type_len = 16 + 36;
+    assert_eq!(
+        good[HDR_TYPE_LEN..HDR_TYPE_LEN + 4],
+        (type_len as u32).to_le_bytes()
+    );
+
+    // leftover bytes too short to be a type
+    let mut data = good.clone();
+    patch_u32(&mut data, HDR_TYPE_LEN, |len| len + 4);
+    match load_err(&data) {
+        BtfError::TruncatedType { offset } => assert_eq!(offset, type_len),
+        err => panic!("unexpected error {}", err),
+    }
+
+    // last member of struct cut off
+    let mut data = good;
+    patch_u32(&mut data, HDR_TYPE_LEN, |len| len - 12);
+    assert!(matches!(
+        load_err(&data),
+        BtfError::TruncatedType { offset: 16 }
+    ));
+}
+
+/// bpf/core_relocs.o is built from bpf/core_relocs.c for BPF target, so it has .BTF.ext with
+/// func info, line info and CO-RE relocation records for `kprobe/test` program
+#[test]
+fn load_ext_records() {
+    let data = std::fs::read(sample_path("bpf/core_relocs.o")).unwrap();
+    let elf = object::File::parse(&*data).unwrap();
+    let btf = Btf::load(&elf).unwrap();
+    let ext = btf.load_ext(&elf).unwrap().unwrap();
+
+    let funcs = &ext.func_infos["kprobe/test"];
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(btf.type_by_id(funcs[0].type_id).name(), "prog");
+    let lines: Vec<(u32, &str)> = ext.line_infos["kprobe/test"]
+        .iter()
+        .map(|l| (l.line_num, l.src_line.trim()))
+        .collect();
+    assert_eq!(
+        lines,
+        [(14, "return __builtin_preserve_access_index(t->mm) != 0;"); 2]
+    );
+    let relos = &ext.core_relos["kprobe/test"];
+    assert_eq!(relos.len(), 1);
+    assert_eq!(relos[0].access_spec, [0, 1]);
+    assert_eq!(btf.full_name(relos[0].type_id), "struct task_struct");
+    // same records as loaded along with BTF itself
+    assert_eq!(btf.core_reloc_secs()[0].recs, *relos);
+
+    let data = std::fs::read(sample_path("golden.o")).unwrap();
+    let elf = object::File::parse(&*data).unwrap();
+    assert!(Btf::load(&elf).unwrap().load_ext(&elf).unwrap().is_none());
+}
+
+#[test]
+fn truncated_ext_records() {
+    let good = std::fs::read(sample_path("bpf/core_relocs.o")).unwrap();
+    let (ext_off, _) = object::File::parse(&*good)
+        .unwrap()
+        .section_by_name(".BTF.ext")
+        .unwrap()
+        .file_range()
+        .unwrap();
+    // func info starts with record size, followed by section name offset and number of
+    // records of the only section, which has a single 8-byte record
+    let rd = |off: usize| u32::from_le_bytes(good[off..off + 4].try_into().unwrap()) as usize;
+    let ext_off = ext_off as usize;
+    let func_info = ext_off + rd(ext_off + 4) + rd(ext_off + 8);
+    assert_eq!((rd(func_info), rd(func_info + 8)), (8, 1));
+
+    let ext_err = |data: &[u8]| {
+        let err = Btf::from_elf_bytes(data).unwrap_err();
+        match err.downcast::<BtfError>() {
+            Ok(err) => *err,
+            Err(err) => panic!("unexpected error {}", err),
+        }
+    };
+    // record is read successfully, but its declared size runs past the end of data
+    let mut data = good.clone();
+    patch_u32(&mut data, func_info, |_| 16);
+    assert!(matches!(
+        ext_err(&data),
+        BtfError::TruncatedExtInfo {
+            info: "func info",
+            offset: 12
+        }
+    ));
+
+    // size of all records overflows 32 bits
+    let mut data = good;
+    patch_u32(&mut data, func_info, |_| 0x1000);
+    patch_u32(&mut data, func_info + 8, |_| 0x10_0000);
+    assert!(matches!(
+        ext_err(&data),
+        BtfError::TruncatedExtInfo {
+            info: "func info",
+            ..
+        }
+    ));
+}
+
+/// Raw little-endian split BTF on top of `int_btf()`: `typedef int myint` and `myint *`
+fn split_btf() -> Vec<u8> {
+    const BTF_KIND_PTR: u32 = 2;
+    const BTF_KIND_TYPEDEF: u32 = 8;
+    let base_str_len = 5; // "\0int\0"
+    let types = [
+        [base_str_len + 1, BTF_KIND_TYPEDEF << 24, 1],
+        [0, BTF_KIND_PTR << 24, 2],
+    ];
+    let strs = b"\0myint\0";
+    let mut data = Vec::new();
+    data.extend_from_slice(&0xeb9fu16.to_le_bytes());
+    data.extend_from_slice(&[1, 0]);
+    for v in [24, 0, 24, 24, strs.len() as u32] {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    for v in types.iter().flatten() {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    data.extend_from_slice(strs);
+    data
+}
+
+#[test]
+fn split_btf_refers_to_base() {
+    let base_data = int_btf();
+    let base = Btf::load_from_bytes(&base_data, 8, scroll::LE).unwrap();
+    let data = split_btf();
+    let split = Btf::load_split(&base, &data).unwrap();
+
+    assert_eq!((split.base_type_cnt(), split.type_cnt()), (2, 4));
+    // base types aren't copied, but resolve through base
+    assert_eq!(split.types().len(), 2);
+    assert!(std::ptr::eq(split.type_by_id(1), base.type_by_id(1)));
+    assert_eq!(split.iter().count(), 4);
+    assert_eq!(split.format_type(3), "[3] <PTR> --> [2] (typedef myint)");
+    assert_eq!(split.id_by_name(Some(BtfKind::Int), "int"), Some(1));
+    assert_eq!(split.id_by_name(None, "myint"), Some(2));
+    assert!(split.type_by_id_opt(4).is_none());
+
+    // own types can't reference IDs past the end of split BTF
+    let mut data = split_btf();
+    patch_u32(&mut data, 24 + 12 + 8, |_| 4);
+    match Btf::load_split(&base, &data)
+        .unwrap_err()
+        .downcast::<BtfError>()
+    {
+        Ok(err) => assert!(matches!(*err, BtfError::BadTypeId { id: 3, type_id: 4 })),
+        Err(err) => panic!("unexpected error {}", err),
+    }
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use btf::types::BtfCoreRelocKind;
+
+#[test]
+fn core_relo_target_paths() {
+    let owned = common::load_sample("bpf/core_relocs.o");
+    let btf = owned.btf();
+    let relo = &btf.core_reloc_secs()[0].recs[0];
+    assert_eq!(relo.access_spec_str, "0:1");
+    assert_eq!(btf.core_relo_target(relo).unwrap(), "task_struct.mm");
+
+    let mut relo = relo.clone();
+    relo.access_spec = vec![2, 1];
+    assert_eq!(btf.core_relo_target(&relo).unwrap(), "task_struct[2].mm");
+    relo.kind = BtfCoreRelocKind::TypeSize;
+    assert_eq!(btf.core_relo_target(&relo).unwrap(), "struct task_struct");
+
+    // task_struct has only two members
+    relo.kind = BtfCoreRelocKind::ByteOff;
+    relo.access_spec = vec![0, 2];
+    let err = btf.core_relo_target(&relo).unwrap_err().to_string();
+    assert!(err.contains("no accessor #2"), "{}", err);
+    // mm is a pointer, which can't be accessed further without dereferencing
+    relo.access_spec = vec![0, 1, 0];
+    assert!(btf.core_relo_target(&relo).is_err());
+}
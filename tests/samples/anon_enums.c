@@ -0,0 +1,20 @@
+enum {
+	FLAG_A = 1,
+	FLAG_B = 2,
+};
+
+enum {
+	FLAG_C = 4,
+};
+
+struct s {
+	enum {
+		S_X,
+		S_Y,
+	} e;
+	int flags;
+};
+
+int f(struct s *s) {
+	return s->flags & (FLAG_A | FLAG_C);
+}
@@ -0,0 +1,37 @@
+/* anonymous struct/union members have no declarator, so nothing follows closing brace */
+struct anon_members {
+	int kind;
+	union {
+		int i;
+		long l;
+		struct {
+			unsigned char lo;
+			unsigned char hi;
+		};
+	};
+	struct {
+		unsigned int a: 4;
+		unsigned int b: 4;
+		union {
+			void *p;
+			unsigned long v;
+		};
+	};
+	union {
+		int arr[2];
+	} named;
+};
+
+union anon_in_union {
+	struct {
+		int x;
+		int y;
+	};
+	long xy;
+};
+
+int main() {
+	static struct anon_members s;
+	static union anon_in_union u;
+	return 0;
+}
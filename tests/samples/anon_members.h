@@ -0,0 +1,30 @@
+struct anon_members {
+	int kind;
+	union {
+		int i;
+		long int l;
+		struct {
+			unsigned char lo;
+			unsigned char hi;
+		};
+	};
+	struct {
+		unsigned int a: 4;
+		unsigned int b: 4;
+		union {
+			void *p;
+			long unsigned int v;
+		};
+	};
+	union {
+		int arr[2];
+	} named;
+};
+
+union anon_in_union {
+	struct {
+		int x;
+		int y;
+	};
+	long int xy;
+};
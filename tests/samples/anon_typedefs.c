@@ -0,0 +1,35 @@
+struct foo {
+	struct {
+		int a;
+		struct foo *self;
+	} named_field;
+	union {
+		int x;
+		long y;
+	};
+	struct {
+		struct {
+			unsigned char c;
+		} inner;
+	} arr[2];
+	struct {
+		int z;
+	} *ptr;
+};
+
+typedef struct {
+	int t;
+} typedefed_t;
+
+struct bar {
+	typedefed_t td;
+	struct {
+		struct bar *back;
+	} b;
+};
+
+typedef int __anon_1;
+
+struct foo f;
+struct bar b;
+__anon_1 c;
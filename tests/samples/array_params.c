@@ -0,0 +1,19 @@
+/* all of these are encoded in BTF as (qualified) pointers */
+typedef void (*arr_param_fn)(int a[4]);
+typedef void (*static_arr_param_fn)(int a[static 4]);
+typedef void (*const_arr_param_fn)(int a[const 4]);
+typedef void (*restrict_arr_param_fn)(int a[restrict 4]);
+typedef void (*const_elem_arr_param_fn)(const int a[4]);
+
+struct arr_params {
+	arr_param_fn a;
+	static_arr_param_fn b;
+	const_arr_param_fn c;
+	restrict_arr_param_fn d;
+	const_elem_arr_param_fn e;
+};
+
+int main() {
+	static struct arr_params s;
+	return 0;
+}
@@ -0,0 +1,17 @@
+typedef void (*arr_param_fn)(int *);
+
+typedef void (*static_arr_param_fn)(int *);
+
+typedef void (*const_arr_param_fn)(int * const);
+
+typedef void (*restrict_arr_param_fn)(int * restrict);
+
+typedef void (*const_elem_arr_param_fn)(const int *);
+
+struct arr_params {
+	arr_param_fn a;
+	static_arr_param_fn b;
+	const_arr_param_fn c;
+	restrict_arr_param_fn d;
+	const_elem_arr_param_fn e;
+};
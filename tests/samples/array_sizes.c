@@ -0,0 +1,22 @@
+typedef int row[4];
+
+struct s {
+	row m[3];
+	unsigned char c;
+};
+
+/* 8GB, size doesn't fit in u32 */
+typedef unsigned char huge[1 << 20][1 << 13];
+
+struct empty {};
+
+/* element count overflows u64, but size is zero */
+typedef struct empty none[1 << 20][1 << 20][1 << 20][1 << 20][0];
+
+struct h {
+	huge *p;
+	none *n;
+	struct s s;
+};
+
+struct h x;
@@ -0,0 +1,22 @@
+/*
+ * big-endian fixture: big_endian.btf is BTF of this file (built with gcc -gbtf) re-encoded as
+ * big-endian with Btf::to_bytes_with_endian(scroll::BE); `btf dump -f c big_endian.btf` has to
+ * match big_endian.h byte for byte, same as dump of little-endian original
+ */
+enum signed_vals {
+	SV_NEG = -2,
+	SV_POS = 0x12345678,
+};
+
+struct flags {
+	unsigned int a: 1;
+	unsigned int b: 7;
+	unsigned int c: 24;
+	unsigned long long d: 40;
+	enum signed_vals sv;
+	double ratio;
+	int (*cb)(struct flags *f, long arg);
+	short arr[3];
+};
+
+struct flags be_flags;
@@ -0,0 +1,16 @@
+enum signed_vals {
+	SV_NEG = 4294967294ULL,
+	SV_POS = 305419896,
+};
+
+struct flags {
+	unsigned int a: 1;
+	unsigned int b: 7;
+	unsigned int c: 24;
+	int: 32;
+	long long unsigned int d: 40;
+	enum signed_vals sv;
+	double ratio;
+	int (*cb)(struct flags *, long int);
+	short int arr[3];
+};
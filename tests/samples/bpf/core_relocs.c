@@ -0,0 +1,15 @@
+struct mm_struct {
+	int users;
+};
+struct task_struct {
+	int pid;
+	struct mm_struct *mm;
+};
+
+#define SEC(NAME) __attribute__((section(NAME), used))
+
+SEC("kprobe/test")
+int prog(struct task_struct *t)
+{
+	return __builtin_preserve_access_index(t->mm) != 0;
+}
@@ -0,0 +1,12 @@
+struct cacheline_test {
+	long a[7];
+	int b;
+	long straddling;
+	long c[6];
+	int d;
+} __attribute__((packed));
+
+int main() {
+	static struct cacheline_test s1;
+	return 0;
+}
@@ -0,0 +1,27 @@
+/* Build twice, with and without -DFWD_ONLY, and link with `ld -r` to get duplicated types */
+struct a;
+
+struct b {
+	struct a *a;
+	int x;
+};
+
+#ifdef FWD_ONLY
+struct c;
+#else
+struct c {
+	struct b *b;
+	long y;
+};
+#endif
+
+struct a {
+	struct b *b;
+	struct c *c;
+};
+
+#ifdef FWD_ONLY
+struct a *g(struct a *a) { return a; }
+#else
+struct a *f(struct a *a) { return a; }
+#endif
@@ -0,0 +1,36 @@
+/* anonymous structs nested 15 levels deep, beyond fast-path indentation */
+struct deep {
+	struct {
+		struct {
+			struct {
+				struct {
+					struct {
+						struct {
+							struct {
+								struct {
+									struct {
+										struct {
+											struct {
+												struct {
+													struct {
+														struct {
+															struct {
+																int leaf;
+															} l15;
+														} l14;
+													} l13;
+												} l12;
+											} l11;
+										} l10;
+									} l9;
+								} l8;
+							} l7;
+						} l6;
+					} l5;
+				} l4;
+			} l3;
+		} l2;
+	} l1;
+};
+
+struct deep v;
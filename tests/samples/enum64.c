@@ -0,0 +1,21 @@
+/* 64-bit enums are encoded as BTF_KIND_ENUM64 by compilers supporting it */
+enum big {
+	B1 = 1,
+	B2 = 0xffffffffffULL,
+	B3 = 0xffffffffffffffffULL,
+};
+
+enum sbig {
+	S1 = -1,
+	S2 = -0x10000000000LL,
+};
+
+struct enums64 {
+	enum big b;
+	enum sbig sb;
+};
+
+int main() {
+	static struct enums64 s;
+	return 0;
+}
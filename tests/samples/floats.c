@@ -0,0 +1,15 @@
+typedef double real_t;
+
+struct floats {
+	float f;
+	double d;
+	long double ld;
+	real_t r;
+	const float *fp;
+	double arr[2];
+};
+
+int main() {
+	static struct floats s;
+	return 0;
+}
@@ -0,0 +1,19 @@
+typedef const int (*const_ret_fn)(void);
+typedef const int *(*ptr_to_const_ret_fn)(int);
+typedef int *const (*const_ptr_ret_fn)(void);
+typedef const int const_ret_proto(void);
+
+struct const_ret_ops {
+	const int (*get)(void);
+	const int *(*get_ptr)(int x);
+	const long *const (*get_cptr)(void);
+	const_ret_proto *get2;
+};
+
+int main() {
+	static const_ret_fn f1;
+	static ptr_to_const_ret_fn f2;
+	static const_ptr_ret_fn f3;
+	static struct const_ret_ops ops;
+	return 0;
+}
@@ -0,0 +1,14 @@
+typedef int (*const_ret_fn)();
+
+typedef const int *(*ptr_to_const_ret_fn)(int);
+
+typedef int *(*const_ptr_ret_fn)();
+
+typedef int const_ret_proto();
+
+struct const_ret_ops {
+	int (*get)();
+	const int *(*get_ptr)(int);
+	const long int *(*get_cptr)();
+	const_ret_proto *get2;
+};
@@ -0,0 +1,15 @@
+typedef int (*no_args_fn)(void);
+typedef int (*unspec_args_fn)();
+typedef int (*varargs_fn)(int, ...);
+
+struct s {
+	no_args_fn a;
+	unspec_args_fn b;
+	varargs_fn c;
+	void (*d)(void);
+};
+
+int main() {
+	static struct s s;
+	return 0;
+}
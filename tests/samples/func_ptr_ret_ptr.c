@@ -0,0 +1,19 @@
+typedef int *(*fn_ret_ptr)(void);
+typedef int (*(*fn_ret_fn_ptr)(void))(void);
+typedef long *(*(*fn_ret_ptr_fn_ret_ptr)(int))(long);
+typedef int (*(*arr_of_fn_ret_fn_ptr[2])(void))(void);
+
+struct s {
+	int *(*a)(void);
+	int (*(*b)(void))(void);
+	const long *(*(*c)(int))(long);
+	fn_ret_ptr d;
+	fn_ret_fn_ptr e;
+	fn_ret_ptr_fn_ret_ptr f;
+	arr_of_fn_ret_fn_ptr g;
+};
+
+int main() {
+	static struct s s;
+	return 0;
+}
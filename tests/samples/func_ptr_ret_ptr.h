@@ -0,0 +1,17 @@
+typedef int *(*fn_ret_ptr)();
+
+typedef int (*(*fn_ret_fn_ptr)())();
+
+typedef long int *(*(*fn_ret_ptr_fn_ret_ptr)(int))(long int);
+
+typedef int (*(*arr_of_fn_ret_fn_ptr[2])())();
+
+struct s {
+	int *(*a)();
+	int (*(*b)())();
+	const long int *(*(*c)(int))(long int);
+	fn_ret_ptr d;
+	fn_ret_fn_ptr e;
+	fn_ret_ptr_fn_ret_ptr f;
+	arr_of_fn_ret_fn_ptr g;
+};
@@ -0,0 +1,26 @@
+/* golden test: `btf dump -f c golden.o` has to match golden.h byte for byte */
+typedef unsigned int u32;
+
+enum state {
+	S_IDLE = 0,
+	S_BUSY = 1,
+};
+
+struct node;
+
+typedef int (*visit_fn)(struct node *n, void *ctx);
+
+struct node {
+	struct node *next;
+	enum state state;
+	u32 flags: 3;
+	u32 refs: 13;
+	union {
+		long key;
+		const void *data;
+	};
+	visit_fn visit;
+	short tag[4];
+};
+
+struct node golden_root;
@@ -0,0 +1,23 @@
+typedef unsigned int u32;
+
+enum state {
+	S_IDLE = 0,
+	S_BUSY = 1,
+};
+
+struct node;
+
+typedef int (*visit_fn)(struct node *, void *);
+
+struct node {
+	struct node *next;
+	enum state state;
+	u32 flags: 3;
+	u32 refs: 13;
+	union {
+		long int key;
+		const void *data;
+	};
+	visit_fn visit;
+	short int tag[4];
+};
@@ -0,0 +1,36 @@
+#ifndef __VMLINUX_H__
+#define __VMLINUX_H__
+
+#ifndef BPF_NO_PRESERVE_ACCESS_INDEX
+#pragma clang attribute push (__attribute__((preserve_access_index)), apply_to = record)
+#endif
+
+typedef unsigned int u32;
+
+enum state {
+	S_IDLE = 0,
+	S_BUSY = 1,
+};
+
+struct node;
+
+typedef int (*visit_fn)(struct node *, void *);
+
+struct node {
+	struct node *next;
+	enum state state;
+	u32 flags: 3;
+	u32 refs: 13;
+	union {
+		long int key;
+		const void *data;
+	};
+	visit_fn visit;
+	short int tag[4];
+};
+
+#ifndef BPF_NO_PRESERVE_ACCESS_INDEX
+#pragma clang attribute pop
+#endif
+
+#endif /* __VMLINUX_H__ */
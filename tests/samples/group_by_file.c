@@ -0,0 +1,8 @@
+struct req { int a; };
+typedef struct req req_t;
+struct other { long x; };
+int handle(req_t *r) { return r->a; }
+int use_other(struct other *o) { return o->x; }
+struct unused { int z; };
+static struct unused u;
+int main() { return 0; }
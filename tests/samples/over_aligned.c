@@ -0,0 +1,24 @@
+struct cacheline {
+	long a;
+	int b;
+} __attribute__((aligned(64)));
+
+struct outer {
+	unsigned char c;
+	struct cacheline line;
+	int d;
+};
+
+struct packed_aligned {
+	unsigned char a;
+	int b;
+} __attribute__((packed, aligned(4)));
+
+struct tail_pad {
+	int a;
+	unsigned char b;
+} __attribute__((aligned(16)));
+
+struct outer v1;
+struct packed_aligned v2;
+struct tail_pad v3;
@@ -0,0 +1,14 @@
+struct inner {
+	unsigned char a;
+	int b;
+} __attribute__((packed));
+
+struct outer {
+	unsigned char c;
+	struct inner in;
+	short s;
+	struct inner arr[2];
+	long l;
+};
+
+struct outer v;
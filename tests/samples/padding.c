@@ -0,0 +1,31 @@
+/* 4 bytes hole */
+struct pad1 {
+	int a;
+	long b;
+};
+
+/* 4 bytes of tail padding */
+struct pad2 {
+	long a;
+	int b;
+};
+
+/* 4 bytes hole and 4 bytes of tail padding */
+struct pad3 {
+	int a;
+	long b;
+	int c;
+};
+
+struct nopad {
+	long a;
+	long b;
+};
+
+int main() {
+	static struct pad1 a;
+	static struct pad2 b;
+	static struct pad3 c;
+	static struct nopad d;
+	return 0;
+}
@@ -0,0 +1,24 @@
+/* enums narrower than int keep their size, e.g., packed ones or ones with fixed underlying type */
+enum __attribute__((packed)) byte_enum {
+	BE1 = 1,
+	BE2 = 0x7f,
+};
+
+enum __attribute__((packed)) short_enum {
+	SE1 = -1,
+	SE2 = 0x7fff,
+};
+
+struct small_enums {
+	unsigned char c;
+	enum byte_enum b;
+	enum short_enum s;
+	enum byte_enum arr[3];
+};
+
+_Static_assert(sizeof(struct small_enums) == 8, "small_enums");
+
+int main() {
+	static struct small_enums s;
+	return 0;
+}
@@ -0,0 +1,14 @@
+/* type tags are only emitted into BTF by clang */
+#define __user __attribute__((btf_type_tag("user")))
+#define __rcu __attribute__((btf_type_tag("rcu")))
+
+struct type_tags {
+	const char __user *a;
+	int __rcu *b;
+	int __user * __rcu *c;
+};
+
+int main() {
+	static struct type_tags s;
+	return 0;
+}
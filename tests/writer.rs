@@ -0,0 +1,72 @@
+mod common;
+
+use btf::types::{Btf, BtfKind};
+
+use common::sample_path;
+
+/// Sample objects (ELF files) in tests/samples
+fn sample_objects() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(sample_path(""))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".o"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn to_bytes_round_trip() {
+    let names = sample_objects();
+    assert!(!names.is_empty());
+    for name in names {
+        let owned = common::load_sample(&name);
+        let btf = owned.btf();
+        let bytes = btf.to_bytes().unwrap();
+        let reloaded = Btf::load_from_bytes(&bytes, btf.ptr_sz(), btf.endian()).unwrap();
+        assert_eq!(reloaded.types(), btf.types(), "{}", name);
+        // re-encoding is stable
+        assert_eq!(reloaded.to_bytes().unwrap(), bytes, "{}", name);
+    }
+}
+
+#[test]
+fn to_bytes_with_endian_round_trip() {
+    for name in sample_objects() {
+        let owned = common::load_sample(&name);
+        let btf = owned.btf();
+        let native = btf.to_bytes().unwrap();
+        let swapped = btf.to_bytes_with_endian(scroll::BE).unwrap();
+        assert_ne!(swapped, native, "{}", name);
+        assert_eq!(swapped.len(), native.len(), "{}", name);
+
+        let reloaded = Btf::load_from_bytes(&swapped, btf.ptr_sz(), scroll::BE).unwrap();
+        assert_eq!(reloaded.endian(), scroll::BE);
+        assert_eq!(reloaded.types(), btf.types(), "{}", name);
+        // swapping back produces original encoding
+        let swapped_back = reloaded.to_bytes_with_endian(scroll::LE).unwrap();
+        assert_eq!(swapped_back, native, "{}", name);
+    }
+}
+
+#[test]
+fn subset_round_trip() {
+    let owned = common::load_sample("golden.o");
+    let btf = owned.btf();
+    let node = btf.id_by_name(Some(BtfKind::Struct), "node").unwrap();
+    let subset = btf.subset(&std::iter::once(node).collect()).unwrap();
+    assert!(subset.type_cnt() < btf.type_cnt());
+
+    let bytes = subset.to_bytes().unwrap();
+    assert!(bytes.len() < btf.to_bytes().unwrap().len());
+    let reloaded = Btf::load_from_bytes(&bytes, btf.ptr_sz(), btf.endian()).unwrap();
+    assert_eq!(reloaded.types(), subset.types());
+    reloaded.validate().unwrap();
+
+    // everything struct node depends on is carried over, and nothing else
+    let node = reloaded.id_by_name(Some(BtfKind::Struct), "node").unwrap();
+    let reachable = reloaded.reachable_from(Some(node));
+    assert_eq!(reachable.len() as u32, reloaded.type_cnt());
+    assert!(reloaded.id_by_name(None, "visit_fn").is_some());
+    assert!(reloaded.id_by_name(None, "golden_root").is_none());
+}